@@ -0,0 +1,119 @@
+use std::time::Duration;
+
+const MAX_SUBSAMPLE: usize = 8;
+// frames in a row with headroom before the resolution goes back up
+const RELAX_AFTER: u32 = 30;
+
+/// Holds the render time per frame near `target` by trading resolution:
+/// raises the subsample factor as soon as a frame runs over, and lowers it
+/// again once frames would fit at the finer factor for a while.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Budget {
+    pub target: Duration,
+    headroom: u32,
+}
+
+impl Budget {
+    pub fn new(target: Duration) -> Self {
+        Self { target, headroom: 0 }
+    }
+
+    /// The subsample factor for the next frame after one at `current` took
+    /// `elapsed`.
+    pub fn next_subsample(&mut self, elapsed: Duration, current: usize) -> usize {
+        let current = current.max(1);
+        if elapsed > self.target {
+            self.headroom = 0;
+            return (current + 1).min(MAX_SUBSAMPLE);
+        }
+        if current == 1 {
+            return 1;
+        }
+        // marched cells, and so time, grow with 1/n²
+        let finer = (current - 1) as f64;
+        let estimate = elapsed.as_secs_f64() * (current as f64 / finer).powi(2);
+        if estimate < self.target.as_secs_f64() * 0.9 {
+            self.headroom += 1;
+        } else {
+            self.headroom = 0;
+        }
+        if self.headroom >= RELAX_AFTER {
+            self.headroom = 0;
+            return current - 1;
+        }
+        current
+    }
+}
+
+// subsample factor right after input, and quiet frames per refinement step
+const COARSE: usize = 4;
+const SETTLE_FRAMES: u32 = 4;
+
+/// Progressive refinement: coarse frames while the user is interacting,
+/// sharpening one subsample level every `SETTLE_FRAMES` quiet frames until
+/// full resolution.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Refine {
+    quiet: u32,
+}
+
+impl Refine {
+    /// Drops back to the coarsest level.
+    pub fn input(&mut self) {
+        self.quiet = 0;
+    }
+
+    /// The subsample factor for the next frame.
+    pub fn next_subsample(&mut self) -> usize {
+        let level = COARSE.saturating_sub((self.quiet / SETTLE_FRAMES) as usize).max(1);
+        self.quiet = self.quiet.saturating_add(1);
+        level
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slow_frames_coarsen_and_fast_frames_refine() {
+        let ms = Duration::from_millis;
+        let mut budget = Budget::new(ms(10));
+        let mut subsample = 1;
+        // rising frame times push the factor up one level per frame
+        for expected in 2..=MAX_SUBSAMPLE {
+            subsample = budget.next_subsample(ms(15), subsample);
+            assert_eq!(subsample, expected);
+        }
+        assert_eq!(budget.next_subsample(ms(15), subsample), MAX_SUBSAMPLE);
+
+        // falling ones bring it back down, one level per RELAX_AFTER frames
+        for expected in (1..MAX_SUBSAMPLE).rev() {
+            for _ in 1..RELAX_AFTER {
+                assert_eq!(budget.next_subsample(ms(1), subsample), subsample);
+            }
+            subsample = budget.next_subsample(ms(1), subsample);
+            assert_eq!(subsample, expected);
+        }
+        assert_eq!(budget.next_subsample(ms(1), subsample), 1);
+    }
+
+    #[test]
+    fn refinement_sharpens_while_quiet_and_input_resets_it() {
+        let mut refine = Refine::default();
+        let levels: Vec<usize> = (0..5 * SETTLE_FRAMES).map(|_| refine.next_subsample()).collect();
+        let expected: Vec<usize> = [4, 3, 2, 1, 1]
+            .into_iter()
+            .flat_map(|level| std::iter::repeat_n(level, SETTLE_FRAMES as usize))
+            .collect();
+        assert_eq!(levels, expected);
+        // any input drops straight back to the coarsest level
+        refine.input();
+        assert_eq!(refine.next_subsample(), COARSE);
+        for _ in 1..SETTLE_FRAMES {
+            refine.next_subsample();
+        }
+        refine.input();
+        assert_eq!(refine.next_subsample(), COARSE);
+    }
+}
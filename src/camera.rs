@@ -0,0 +1,208 @@
+use std::f32::consts::FRAC_PI_2;
+
+use crate::math::{Mat3, Vec3};
+use crate::rng::Rng;
+
+// keep clear of the poles, where azimuth degenerates
+const MAX_ELEVATION: f32 = FRAC_PI_2 * 0.95;
+const MIN_DISTANCE: f32 = 0.5;
+const MAX_DISTANCE: f32 = 20.0;
+
+/// Vertical field of view: rays leave through `(1, u, v)` with `v` in
+/// `[-1, 1]`, i.e. 45 degrees either side of forward.
+pub const FOV: f32 = FRAC_PI_2;
+
+/// Camera distance at which a bounding sphere of `radius` fills `fill` of
+/// the field of view `fov`, both in radians; `fill` of 1 touches the edges.
+pub fn fit_distance(radius: f32, fov: f32, fill: f32) -> f32 {
+    radius / (fill * fov / 2.0).sin()
+}
+
+/// Orbit camera looking at `target` from `distance` away. At zero azimuth
+/// and elevation it sits on the -X axis looking down +X, with +Y to the
+/// right of the screen and +Z down, like the original C++ camera.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Camera {
+    pub target: Vec3,
+    // radians around the screen's vertical (Z) axis
+    pub azimuth: f32,
+    // radians above the horizon
+    pub elevation: f32,
+    pub distance: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self { target: Vec3::default(), azimuth: 0.0, elevation: 0.0, distance: 2.5 }
+    }
+}
+
+impl Camera {
+    /// Columns are the forward, right and down directions.
+    pub fn basis(&self) -> Mat3 {
+        // +Z points down, so a raised camera tilts its forward toward +Z
+        Mat3::rotation_z(self.azimuth) * Mat3::rotation_y(-self.elevation)
+    }
+
+    pub fn position(&self) -> Vec3 {
+        self.target.sub(self.basis().col(0).mul(self.distance))
+    }
+
+    pub fn orbit(&mut self, d_azimuth: f32, d_elevation: f32) {
+        self.azimuth += d_azimuth;
+        self.elevation = (self.elevation + d_elevation).clamp(-MAX_ELEVATION, MAX_ELEVATION);
+    }
+
+    /// Moves to where a bounding sphere of `radius` around the target fills
+    /// `fill` of the view, within the usual dolly range.
+    pub fn fit(&mut self, radius: f32, fill: f32) {
+        self.distance = fit_distance(radius, FOV, fill).clamp(MIN_DISTANCE, MAX_DISTANCE);
+    }
+
+    /// Moves toward (`factor < 1`) or away from the target.
+    pub fn dolly(&mut self, factor: f32) {
+        self.distance = (self.distance * factor).clamp(MIN_DISTANCE, MAX_DISTANCE);
+    }
+}
+
+/// Seeded camera jitter for intros: the target wanders by up to
+/// `amplitude` and the view turns by about as much at the camera, with new
+/// offsets `frequency` times per frame eased into one another, all fading
+/// out over the first `duration` frames.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Shake {
+    pub amplitude: f32,
+    pub frequency: f32,
+    pub duration: u32,
+}
+
+impl Shake {
+    /// Share of the amplitude left at `frame`: 1 at the start, easing to 0
+    /// at `duration` and staying there.
+    pub fn envelope(&self, frame: u32) -> f32 {
+        let left = 1.0 - frame as f32 / self.duration.max(1) as f32;
+        left.max(0.0).powi(2)
+    }
+
+    /// `camera` jolted for `frame`, the same for the same `seed`.
+    pub fn apply(&self, camera: Camera, frame: u32, seed: u64) -> Camera {
+        let strength = self.amplitude * self.envelope(frame);
+        if strength == 0.0 {
+            return camera;
+        }
+        let x = frame as f32 * self.frequency;
+        let (key, f) = (x.floor() as u64, x.fract());
+        let ease = f * f * (3.0 - 2.0 * f);
+        // noise in [-1, 1) per axis, smoothstepped between whole keys
+        let noise = |axis: u64| {
+            let at = |key: u64| {
+                let hash = key.wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ axis.wrapping_mul(0xd1b5_4a32_d192_ed03);
+                Rng::new(seed ^ hash).range(-1.0, 1.0)
+            };
+            at(key) + (at(key + 1) - at(key)) * ease
+        };
+        let offset = Vec3::new(noise(0), noise(1), noise(2)).mul(strength);
+        // turning by strength / distance moves the camera about as far
+        let turn = strength / camera.distance;
+        Camera {
+            target: camera.target.add(offset),
+            azimuth: camera.azimuth + noise(3) * turn,
+            elevation: (camera.elevation + noise(4) * turn).clamp(-MAX_ELEVATION, MAX_ELEVATION),
+            ..camera
+        }
+    }
+}
+
+/// What the animation turns: the scene in front of a still camera, or the
+/// camera around a still scene. The two look alike, lighting aside, but one
+/// rotates every SDF sample and the other only the ray origin and basis.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Spin {
+    #[default]
+    Shape,
+    Camera,
+}
+
+impl Spin {
+    pub const ALL: [Spin; 2] = [Spin::Shape, Spin::Camera];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Spin::Shape => "shape",
+            Spin::Camera => "camera",
+        }
+    }
+
+    /// The world-to-object rotation and the camera's view rotation for a
+    /// scene spin whose inverse is `unspin`; spinning the camera turns it
+    /// the opposite way around the still scene.
+    pub fn rotations(self, unspin: Mat3) -> (Mat3, Mat3) {
+        match self {
+            Spin::Shape => (unspin, Mat3::identity()),
+            Spin::Camera => (Mat3::identity(), unspin),
+        }
+    }
+}
+
+impl std::str::FromStr for Spin {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Spin::ALL
+            .into_iter()
+            .find(|m| m.name() == s)
+            .ok_or_else(|| format!("unknown spin mode: {s} (expected shape or camera)"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_spin_mode_turns_only_its_own_side() {
+        let camera = Camera::default();
+        let axis = Vec3::new(1.0, 1.0, 1.0).norm();
+        // the torus axis (object space) in the world, and the ray origin
+        let frame = |spin: Spin, angle: f32| {
+            let unspin = Mat3::from_axis_angle(Vec3::new(0.0, 0.0, 1.0), angle).transpose();
+            let (to_object, view) = spin.rotations(unspin);
+            (to_object.transpose() * axis, view * camera.position())
+        };
+        let moved = |a: Vec3, b: Vec3| a.sub(b).len() > 0.1;
+        let ((tdir0, eye0), (tdir1, eye1)) = (frame(Spin::Shape, 0.0), frame(Spin::Shape, 0.8));
+        assert!(moved(tdir0, tdir1) && !moved(eye0, eye1));
+        let ((tdir0, eye0), (tdir1, eye1)) = (frame(Spin::Camera, 0.0), frame(Spin::Camera, 0.8));
+        assert!(!moved(tdir0, tdir1) && moved(eye0, eye1));
+    }
+
+    #[test]
+    fn fit_distance_scales_with_the_radius() {
+        let unit = fit_distance(1.0, FOV, 0.8);
+        for radius in [0.5, 1.5, 2.0, 4.0] {
+            assert!((fit_distance(radius, FOV, 0.8) - radius * unit).abs() < 1e-5);
+        }
+        // filling the whole 90 degree view puts the sphere's edge on the view's
+        assert!((fit_distance(1.0, FOV, 1.0) - std::f32::consts::SQRT_2).abs() < 1e-6);
+    }
+
+    #[test]
+    fn shake_dies_down_by_the_end_of_its_duration() {
+        let shake = Shake { amplitude: 0.3, frequency: 0.25, duration: 60 };
+        let camera = Camera::default();
+        let offset = |frame| shake.apply(camera, frame, 7).target.sub(camera.target).len();
+        assert_eq!(shake.envelope(0), 1.0);
+        assert!((1..=60).all(|f| shake.envelope(f) < shake.envelope(f - 1)));
+        assert!(shake.envelope(57) < 0.01);
+        // the largest jolt near the end is tiny next to the one at the start
+        let late = (55..60).map(offset).fold(0.0, f32::max);
+        assert!(late < 0.01, "{late}");
+        assert!(offset(2) > 10.0 * late);
+        // and past it the camera is exactly where it was
+        assert_eq!(shake.apply(camera, 60, 7), camera);
+        assert_eq!(shake.apply(camera, 500, 7), camera);
+    }
+}
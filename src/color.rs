@@ -0,0 +1,824 @@
+use crossterm::style::Color;
+
+use crate::math::Vec3;
+
+// Helper to linearly interpolate between two u8 values
+pub fn lerp(a: u8, b: u8, t: f32) -> u8 {
+    (a as f32 + (b as f32 - a as f32) * t).round().clamp(0.0, 255.0) as u8
+}
+
+// Helper to linearly interpolate between two Color::Rgb
+pub fn lerp_color(a: &Color, b: &Color, t: f32) -> Color {
+    match (a, b) {
+        (Color::Rgb { r: r1, g: g1, b: b1 }, Color::Rgb { r: r2, g: g2, b: b2 }) => {
+            Color::Rgb {
+                r: lerp(*r1, *r2, t),
+                g: lerp(*g1, *g2, t),
+                b: lerp(*b1, *b2, t),
+            }
+        }
+        _ => *a, // fallback, should not happen
+    }
+}
+
+/// `base` with `light` scaled by `amount` added on top, per channel in
+/// linear `[0, 1]` and fitted back under white by `tonemap` before it is
+/// quantized.
+pub fn add_light(base: Color, light: Color, amount: f32, tonemap: Tonemap) -> Color {
+    let ((r1, g1, b1), (r2, g2, b2)) = (to_rgb(base), to_rgb(light));
+    let add = |a: u8, b: u8| {
+        let sum = tonemap.add(a as f32 / 255.0, b as f32 / 255.0 * amount.max(0.0));
+        (sum.clamp(0.0, 1.0) * 255.0).round() as u8
+    };
+    Color::Rgb { r: add(r1, r2), g: add(g1, g2), b: add(b1, b2) }
+}
+
+// Gradient stops: (threshold, Color)
+// Blue to orange temperature gradient
+pub const GRADIENT: &[(f32, Color)] = &[
+    (0.0,  Color::Rgb { r: 0,   g: 0,   b: 150 }),   // Deep blue
+    (0.2,  Color::Rgb { r: 50,  g: 100, b: 255 }),   // Medium blue
+    (0.4,  Color::Rgb { r: 100, g: 200, b: 255 }),   // Cyan
+    (0.6,  Color::Rgb { r: 200, g: 255, b: 155 }),   // Yellowish
+    (0.8,  Color::Rgb { r: 255, g: 155, b: 0   }),   // Orange
+    (1.0,  Color::Rgb { r: 255, g: 255, b: 100 }),   // White-orange
+];
+
+/// RGB triple of a color; the terminal default (`Reset`) and other named
+/// colors count as black background.
+pub fn to_rgb(c: Color) -> (u8, u8, u8) {
+    match c {
+        Color::Rgb { r, g, b } => (r, g, b),
+        _ => (0, 0, 0),
+    }
+}
+
+/// `#RRGGBB` form of a color, as used in config files.
+pub fn to_hex(c: Color) -> String {
+    let (r, g, b) = to_rgb(c);
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// Parses `#RRGGBB` (the `#` is optional).
+pub fn parse_hex(s: &str) -> Result<Color, String> {
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    let channel = |i: usize| {
+        hex.get(i..i + 2)
+            .and_then(|c| u8::from_str_radix(c, 16).ok())
+            .ok_or_else(|| format!("invalid color: {s} (expected #RRGGBB)"))
+    };
+    if hex.len() != 6 {
+        return Err(format!("invalid color: {s} (expected #RRGGBB)"));
+    }
+    Ok(Color::Rgb { r: channel(0)?, g: channel(2)?, b: channel(4)? })
+}
+
+/// crossterm's `Color` has no serde support of its own; use with
+/// `#[serde(with = "crate::color::hex")]` to store colors as `#RRGGBB`.
+#[cfg(feature = "serde")]
+pub mod hex {
+    use crossterm::style::Color;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(c: &Color, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&super::to_hex(*c))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Color, D::Error> {
+        let s = String::deserialize(d)?;
+        super::parse_hex(&s).map_err(D::Error::custom)
+    }
+
+    /// The same for an optional color, left out when `None`.
+    pub mod option {
+        use crossterm::style::Color;
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(c: &Option<Color>, s: S) -> Result<S::Ok, S::Error> {
+            match c {
+                Some(c) => super::serialize(c, s),
+                None => s.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Color>, D::Error> {
+            #[derive(Deserialize)]
+            struct Hex(#[serde(with = "super")] Color);
+            Ok(Option::<Hex>::deserialize(d)?.map(|Hex(c)| c))
+        }
+    }
+}
+
+/// RGB to (hue in degrees `[0, 360)`, saturation, value), the latter two in `[0, 1]`.
+pub fn rgb_to_hsv(r: u8, g: u8, b: u8) -> (f32, f32, f32) {
+    let (r, g, b) = (r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+    let max = r.max(g).max(b);
+    let delta = max - r.min(g).min(b);
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+    let sat = if max == 0.0 { 0.0 } else { delta / max };
+    (hue, sat, max)
+}
+
+pub fn hsv_to_rgb(h: f32, s: f32, v: f32) -> (u8, u8, u8) {
+    let h = h.rem_euclid(360.0) / 60.0;
+    let c = v * s;
+    let x = c * (1.0 - (h.rem_euclid(2.0) - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+    let m = v - c;
+    let to_u8 = |f: f32| ((f + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+    (to_u8(r), to_u8(g), to_u8(b))
+}
+
+/// Blend in HSV, taking the shorter way around the hue circle.
+pub fn lerp_color_hsv(a: &Color, b: &Color, t: f32) -> Color {
+    let ((r1, g1, b1), (r2, g2, b2)) = (to_rgb(*a), to_rgb(*b));
+    let (h1, s1, v1) = rgb_to_hsv(r1, g1, b1);
+    let (h2, s2, v2) = rgb_to_hsv(r2, g2, b2);
+    let dh = (h2 - h1 + 540.0).rem_euclid(360.0) - 180.0;
+    let (r, g, b) = hsv_to_rgb(h1 + dh * t, s1 + (s2 - s1) * t, v1 + (v2 - v1) * t);
+    Color::Rgb { r, g, b }
+}
+
+// sRGB transfer function, per channel in [0, 1]
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 }
+}
+
+/// Oklab (L, a, b) of an sRGB color; L runs from 0 (black) to 1 (white) in
+/// roughly even perceptual steps. Matrices from Björn Ottosson's reference.
+#[allow(clippy::excessive_precision)] // the reference constants, verbatim
+pub fn rgb_to_oklab(r: u8, g: u8, b: u8) -> (f32, f32, f32) {
+    let [r, g, b] = [r, g, b].map(|c| srgb_to_linear(c as f32 / 255.0));
+    let l = (0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b).cbrt();
+    let m = (0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b).cbrt();
+    let s = (0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b).cbrt();
+    (
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+    )
+}
+
+#[allow(clippy::excessive_precision)]
+pub fn oklab_to_rgb(l: f32, a: f32, b: f32) -> (u8, u8, u8) {
+    let l_ = (l + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+    let m_ = (l - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+    let s_ = (l - 0.0894841775 * a - 1.2914855480 * b).powi(3);
+    let rgb = [
+        4.0767416621 * l_ - 3.3077115913 * m_ + 0.2309699292 * s_,
+        -1.2684380046 * l_ + 2.6097574011 * m_ - 0.3413193965 * s_,
+        -0.0041960863 * l_ - 0.7034186147 * m_ + 1.7076147010 * s_,
+    ];
+    let [r, g, b] = rgb.map(|c| (linear_to_srgb(c.clamp(0.0, 1.0)) * 255.0).round() as u8);
+    (r, g, b)
+}
+
+/// Blend in Oklab, so equal steps of `t` look like equal steps of
+/// brightness and hue.
+pub fn lerp_color_oklab(a: &Color, b: &Color, t: f32) -> Color {
+    let ((r1, g1, b1), (r2, g2, b2)) = (to_rgb(*a), to_rgb(*b));
+    let (l1, a1, b1) = rgb_to_oklab(r1, g1, b1);
+    let (l2, a2, b2) = rgb_to_oklab(r2, g2, b2);
+    let (r, g, b) = oklab_to_rgb(l1 + (l2 - l1) * t, a1 + (a2 - a1) * t, b1 + (b2 - b1) * t);
+    Color::Rgb { r, g, b }
+}
+
+/// Color space palette stops are blended in.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Interpolation {
+    #[default]
+    Rgb,
+    Hsv,
+    // perceptually even steps
+    Oklab,
+}
+
+impl Interpolation {
+    pub const ALL: [Interpolation; 3] = [Interpolation::Rgb, Interpolation::Hsv, Interpolation::Oklab];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Interpolation::Rgb => "rgb",
+            Interpolation::Hsv => "hsv",
+            Interpolation::Oklab => "oklab",
+        }
+    }
+
+    pub fn lerp(self, a: Color, b: Color, t: f32) -> Color {
+        match self {
+            Interpolation::Rgb => lerp_color(&a, &b, t),
+            Interpolation::Hsv => lerp_color_hsv(&a, &b, t),
+            Interpolation::Oklab => lerp_color_oklab(&a, &b, t),
+        }
+    }
+}
+
+impl std::str::FromStr for Interpolation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Interpolation::ALL
+            .into_iter()
+            .find(|m| m.name() == s)
+            .ok_or_else(|| format!("unknown interpolation: {s} (expected rgb, hsv or oklab)"))
+    }
+}
+
+/// Shifts the hue of a color by `degrees`, keeping saturation and value.
+pub fn rotate_hue(c: Color, degrees: f32) -> Color {
+    match c {
+        Color::Rgb { r, g, b } => {
+            let (h, s, v) = rgb_to_hsv(r, g, b);
+            let (r, g, b) = hsv_to_rgb(h + degrees, s, v);
+            Color::Rgb { r, g, b }
+        }
+        other => other,
+    }
+}
+
+/// Contrast around mid-grey plus a brightness lift, clamped to `[0, 1]`.
+/// `contrast = 1, brightness = 0` leaves in-range values untouched.
+pub fn adjust_intensity(value: f32, contrast: f32, brightness: f32) -> f32 {
+    ((value - 0.5) * contrast + 0.5 + brightness).clamp(0.0, 1.0)
+}
+
+/// What picks a hit's place on the palette besides its lighting.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ColorMap {
+    // the lighting alone
+    #[default]
+    Light,
+    // distance from the screen center: the first stop there, the last in
+    // the corners
+    Radial,
+    // from the top-left corner to the bottom-right one
+    Diagonal,
+}
+
+impl ColorMap {
+    pub const ALL: [ColorMap; 3] = [ColorMap::Light, ColorMap::Radial, ColorMap::Diagonal];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorMap::Light => "light",
+            ColorMap::Radial => "radial",
+            ColorMap::Diagonal => "diagonal",
+        }
+    }
+
+    /// Palette position in `[0, 1]` for the screen point (`x`, `y`), each
+    /// `0..1` from the top-left; None when only the lighting counts.
+    pub fn coordinate(self, x: f32, y: f32) -> Option<f32> {
+        match self {
+            ColorMap::Light => None,
+            ColorMap::Radial => {
+                let (dx, dy) = (x - 0.5, y - 0.5);
+                Some(((dx * dx + dy * dy).sqrt() * std::f32::consts::SQRT_2).min(1.0))
+            }
+            ColorMap::Diagonal => Some(((x + y) / 2.0).clamp(0.0, 1.0)),
+        }
+    }
+}
+
+impl std::str::FromStr for ColorMap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ColorMap::ALL
+            .into_iter()
+            .find(|m| m.name() == s)
+            .ok_or_else(|| format!("unknown color map: {s} (expected light, radial or diagonal)"))
+    }
+}
+
+// Reinhard only bends values above this, so the midtones stay as they are
+const SHOULDER: f32 = 0.8;
+
+/// Curve from the unbounded lighting intensity to the palette's `[0, 1]`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Tonemap {
+    // pass through; the palette lookup clips at its ends
+    #[default]
+    None,
+    // Reinhard `x / (1 + x)` on the part above the shoulder: identity below
+    // it, then rolling off smoothly toward 1 without ever reaching it
+    Reinhard,
+    // hard clip to [0, 1]
+    Clamp,
+}
+
+impl Tonemap {
+    pub const ALL: [Tonemap; 3] = [Tonemap::None, Tonemap::Reinhard, Tonemap::Clamp];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Tonemap::None => "none",
+            Tonemap::Reinhard => "reinhard",
+            Tonemap::Clamp => "clamp",
+        }
+    }
+
+    pub fn apply(self, x: f32) -> f32 {
+        match self {
+            Tonemap::None => x,
+            Tonemap::Reinhard if x > SHOULDER => {
+                // scaled so the slope is 1 where the curve starts
+                let over = (x - SHOULDER) / (1.0 - SHOULDER);
+                SHOULDER + (1.0 - SHOULDER) * over / (1.0 + over)
+            }
+            Tonemap::Reinhard => x,
+            Tonemap::Clamp => x.clamp(0.0, 1.0),
+        }
+    }
+
+    /// `light` added to a channel already at `base`. Reinhard rolls it off
+    /// into the headroom above `base`, with slope 1 at first, so the sum
+    /// nears white without clipping; the others add and leave the clip to
+    /// the caller.
+    pub fn add(self, base: f32, light: f32) -> f32 {
+        let room = 1.0 - base;
+        match self {
+            Tonemap::Reinhard if light > 0.0 && room > 0.0 => base + room * light / (room + light),
+            Tonemap::Reinhard => base,
+            _ => base + light,
+        }
+    }
+}
+
+impl std::str::FromStr for Tonemap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Tonemap::ALL
+            .into_iter()
+            .find(|m| m.name() == s)
+            .ok_or_else(|| format!("unknown tonemap: {s} (expected none, reinhard or clamp)"))
+    }
+}
+
+/// Share of the fog color at distance `dist`: 0 at the camera, rising
+/// monotonically toward 1.
+pub fn fog_factor(density: f32, dist: f32) -> f32 {
+    1.0 - (-density * dist).exp()
+}
+
+/// Relative luminance (Rec. 709) in `[0, 1]`.
+pub fn luminance(c: Color) -> f32 {
+    let (r, g, b) = to_rgb(c);
+    (0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32) / 255.0
+}
+
+// channel levels of the 6x6x6 cube in the xterm 256-color palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+// xterm's defaults for the 16 system colors; terminals often differ
+const ANSI16: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// RGB value of an xterm 256-color palette entry: the 16 system colors
+/// (0..=15) as xterm draws them by default, the color cube (16..=231) or
+/// the grey ramp (232..=255).
+pub fn ansi256_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => ANSI16[index as usize],
+        16..=231 => {
+            let i = (index - 16) as usize;
+            (CUBE_LEVELS[i / 36], CUBE_LEVELS[i / 6 % 6], CUBE_LEVELS[i % 6])
+        }
+        232..=255 => {
+            let v = 8 + 10 * (index - 232);
+            (v, v, v)
+        }
+    }
+}
+
+/// Nearest xterm 256-color palette entry, picking the closer of the best
+/// color cube entry and the best grey.
+pub fn rgb_to_ansi256(r: u8, g: u8, b: u8) -> u8 {
+    let level = |c: u8| {
+        (0..6).min_by_key(|&i| (CUBE_LEVELS[i] as i32 - c as i32).abs()).unwrap_or(0) as u8
+    };
+    let cube = 16 + 36 * level(r) + 6 * level(g) + level(b);
+    let mean = (r as u32 + g as u32 + b as u32) / 3;
+    let grey = 232 + (mean.saturating_sub(3) / 10).min(23) as u8;
+    let dist = |index: u8| {
+        let (pr, pg, pb) = ansi256_rgb(index);
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(pr, r) + d(pg, g) + d(pb, b)
+    };
+    if dist(grey) < dist(cube) { grey } else { cube }
+}
+
+/// `Rgb` colors mapped to their nearest palette entry; others pass through.
+pub fn to_ansi256(c: Color) -> Color {
+    match c {
+        Color::Rgb { r, g, b } => Color::AnsiValue(rgb_to_ansi256(r, g, b)),
+        other => other,
+    }
+}
+
+/// Index of the nearest of the 16 system colors, by their xterm defaults.
+pub fn rgb_to_ansi16(r: u8, g: u8, b: u8) -> u8 {
+    let dist = |&(pr, pg, pb): &(u8, u8, u8)| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(pr, r) + d(pg, g) + d(pb, b)
+    };
+    (0..16).min_by_key(|&i| dist(&ANSI16[i])).unwrap_or(0) as u8
+}
+
+/// How many colors the output may use, to preview a limited terminal.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum ColorDepth {
+    // every color as rendered
+    #[default]
+    Truecolor,
+    // the xterm color cube and grey ramp
+    Ansi256,
+    // the 16 system colors
+    Ansi16,
+    // the terminal's own foreground only
+    Mono,
+}
+
+impl ColorDepth {
+    pub const ALL: [ColorDepth; 4] =
+        [ColorDepth::Truecolor, ColorDepth::Ansi256, ColorDepth::Ansi16, ColorDepth::Mono];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorDepth::Truecolor => "truecolor",
+            ColorDepth::Ansi256 => "ansi256",
+            ColorDepth::Ansi16 => "ansi16",
+            ColorDepth::Mono => "mono",
+        }
+    }
+
+    /// `c` as a terminal of this depth shows it; non-`Rgb` colors pass
+    /// through, except that mono resets everything.
+    pub fn apply(self, c: Color) -> Color {
+        match (self, c) {
+            (ColorDepth::Mono, _) => Color::Reset,
+            (ColorDepth::Ansi256, c) => to_ansi256(c),
+            (ColorDepth::Ansi16, Color::Rgb { r, g, b }) => Color::AnsiValue(rgb_to_ansi16(r, g, b)),
+            (_, c) => c,
+        }
+    }
+}
+
+impl std::str::FromStr for ColorDepth {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ColorDepth::ALL
+            .into_iter()
+            .find(|d| d.name() == s)
+            .ok_or_else(|| format!("unknown color depth: {s} (expected truecolor, ansi256, ansi16 or mono)"))
+    }
+}
+
+/// Debug color for a unit normal: each axis from -1..1 onto 0..255.
+pub fn normal_color(n: Vec3) -> Color {
+    let channel = |v: f32| ((v * 0.5 + 0.5).clamp(0.0, 1.0) * 255.0).round() as u8;
+    Color::Rgb { r: channel(n.x), g: channel(n.y), b: channel(n.z) }
+}
+
+pub fn get_color_from_intensity(intensity: f32) -> Color {
+    sample_gradient(GRADIENT, intensity)
+}
+
+/// Color at `intensity` along a list of (threshold, color) stops.
+pub fn sample_gradient(stops: &[(f32, Color)], intensity: f32) -> Color {
+    sample_gradient_with(stops, intensity, Interpolation::Rgb)
+}
+
+/// `sample_gradient` blending neighbouring stops with `mode`.
+pub fn sample_gradient_with(stops: &[(f32, Color)], intensity: f32, mode: Interpolation) -> Color {
+    let clamped = intensity.clamp(0.0, 1.0);
+
+    // Below the first stop keeps its color
+    let (first, last) = (stops[0], stops[stops.len() - 1]);
+    if clamped <= first.0 {
+        return first.1;
+    }
+
+    // Find the two stops between which clamped falls
+    for pair in stops.windows(2) {
+        let (t0, c0) = pair[0];
+        let (t1, c1) = pair[1];
+        if clamped <= t1 {
+            // stops at the same threshold make a hard edge
+            if t1 <= t0 {
+                return c1;
+            }
+            let t = (clamped - t0) / (t1 - t0);
+            return mode.lerp(c0, c1, t);
+        }
+    }
+
+    // Above the last stop keeps its color
+    last.1
+}
+
+/// Entries in a `Lut`.
+pub const LUT_SIZE: usize = 256;
+
+/// A palette sampled at `LUT_SIZE` evenly spaced intensities, so the per
+/// cell lookup is an index instead of a scan over the gradient stops.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Lut([Color; LUT_SIZE]);
+
+impl Lut {
+    /// Samples `palette` from intensity 0 to 1.
+    pub fn new(palette: impl Fn(f32) -> Color) -> Self {
+        Self(std::array::from_fn(|k| palette(k as f32 / (LUT_SIZE - 1) as f32)))
+    }
+
+    /// The sampled color nearest to `intensity`, clamped to 0..1.
+    pub fn get(&self, intensity: f32) -> Color {
+        self.0[(intensity.clamp(0.0, 1.0) * (LUT_SIZE - 1) as f32).round() as usize]
+    }
+}
+
+const OCEAN: &[(f32, Color)] = &[
+    (0.0,  Color::Rgb { r: 0,   g: 20,  b: 60  }),   // Abyss
+    (0.35, Color::Rgb { r: 0,   g: 90,  b: 140 }),   // Deep water
+    (0.7,  Color::Rgb { r: 40,  g: 190, b: 200 }),   // Teal
+    (1.0,  Color::Rgb { r: 220, g: 255, b: 255 }),   // Foam
+];
+
+const FOREST: &[(f32, Color)] = &[
+    (0.0,  Color::Rgb { r: 10,  g: 40,  b: 10  }),   // Undergrowth
+    (0.4,  Color::Rgb { r: 30,  g: 120, b: 40  }),   // Leaf
+    (0.75, Color::Rgb { r: 150, g: 210, b: 60  }),   // Lime
+    (1.0,  Color::Rgb { r: 250, g: 240, b: 140 }),   // Sunlight
+];
+
+const NEON: &[(f32, Color)] = &[
+    (0.0,  Color::Rgb { r: 40,  g: 0,   b: 80  }),   // Violet
+    (0.4,  Color::Rgb { r: 200, g: 0,   b: 200 }),   // Magenta
+    (0.7,  Color::Rgb { r: 255, g: 80,  b: 160 }),   // Hot pink
+    (1.0,  Color::Rgb { r: 80,  g: 255, b: 255 }),   // Cyan
+];
+
+const MONO: &[(f32, Color)] = &[
+    (0.0,  Color::Rgb { r: 40,  g: 40,  b: 40  }),
+    (1.0,  Color::Rgb { r: 255, g: 255, b: 255 }),
+];
+
+/// Named color gradients the intensity is mapped through.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Theme {
+    #[default]
+    Temperature,
+    Ocean,
+    Forest,
+    Neon,
+    Mono,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 5] = [Theme::Temperature, Theme::Ocean, Theme::Forest, Theme::Neon, Theme::Mono];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Temperature => "temperature",
+            Theme::Ocean => "ocean",
+            Theme::Forest => "forest",
+            Theme::Neon => "neon",
+            Theme::Mono => "mono",
+        }
+    }
+
+    pub fn gradient(self) -> &'static [(f32, Color)] {
+        match self {
+            Theme::Temperature => GRADIENT,
+            Theme::Ocean => OCEAN,
+            Theme::Forest => FOREST,
+            Theme::Neon => NEON,
+            Theme::Mono => MONO,
+        }
+    }
+
+    pub fn color(self, intensity: f32) -> Color {
+        sample_gradient(self.gradient(), intensity)
+    }
+
+    /// The gradient with every stop's hue rotated by `degrees`.
+    pub fn hue_rotated(self, degrees: f32) -> Vec<(f32, Color)> {
+        self.gradient()
+            .iter()
+            .map(|&(t, c)| (t, rotate_hue(c, degrees)))
+            .collect()
+    }
+}
+
+impl std::str::FromStr for Theme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Theme::ALL
+            .into_iter()
+            .find(|t| t.name() == s)
+            .ok_or_else(|| format!("unknown theme: {s}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: Color = Color::Rgb { r: 0, g: 0, b: 0 };
+    const RED: Color = Color::Rgb { r: 255, g: 0, b: 0 };
+    const WHITE: Color = Color::Rgb { r: 255, g: 255, b: 255 };
+
+    #[test]
+    fn gradient_keeps_the_end_colors_outside_its_stops() {
+        let stops = [(0.2, BLACK), (0.8, WHITE)];
+        assert_eq!(sample_gradient(&stops, 0.0), BLACK);
+        assert_eq!(sample_gradient(&stops, 0.1), BLACK);
+        assert_eq!(sample_gradient(&stops, 0.9), WHITE);
+    }
+
+    #[test]
+    fn gradient_steps_at_repeated_thresholds() {
+        let stops = [(0.0, BLACK), (0.5, RED), (0.5, WHITE), (1.0, WHITE)];
+        assert_eq!(sample_gradient(&stops, 0.5), RED);
+        assert_eq!(sample_gradient(&stops, 0.51), WHITE);
+        assert_eq!(sample_gradient(&stops, 0.25), Color::Rgb { r: 128, g: 0, b: 0 });
+    }
+
+    #[test]
+    fn reinhard_keeps_midtones_and_saturates_smoothly() {
+        let map = |x| Tonemap::Reinhard.apply(x);
+        for x in [0.0, 0.3, 0.6, 0.8] {
+            assert_eq!(map(x), x);
+        }
+        let curve: Vec<f32> = (0..100).map(|i| map(0.8 + i as f32 * 0.5)).collect();
+        assert!(curve.windows(2).all(|w| w[0] < w[1] && w[1] < 1.0));
+        assert!(map(50.0) > 0.99);
+    }
+
+    #[test]
+    fn reinhard_rolls_off_additive_light_below_white() {
+        let grey = Color::Rgb { r: 200, g: 200, b: 200 };
+        let (r, _, _) = to_rgb(add_light(grey, WHITE, 0.02, Tonemap::Reinhard));
+        assert!((204..=206).contains(&r));
+        let lit = [0.5, 1.0, 2.0, 8.0].map(|a| to_rgb(add_light(grey, WHITE, a, Tonemap::Reinhard)).0);
+        assert!(lit.windows(2).all(|w| w[0] < w[1]) && lit[3] < 255);
+        assert_eq!(add_light(grey, WHITE, 1.0, Tonemap::None), WHITE);
+        assert_eq!(add_light(grey, WHITE, 0.0, Tonemap::Reinhard), grey);
+    }
+
+    #[test]
+    fn color_depth_limits_the_distinct_colors() {
+        // a 16x16x16 grid through the RGB cube
+        let ramp = |x: u32| [(x % 16 * 17) as u8, (x / 16 % 16 * 17) as u8, (x / 256 * 17) as u8];
+        let colors: Vec<Color> = (0..4096).map(ramp).map(|[r, g, b]| Color::Rgb { r, g, b }).collect();
+        let distinct = |depth: ColorDepth| {
+            let mut seen: Vec<Color> = colors.iter().map(|&c| depth.apply(c)).collect();
+            seen.sort_by_key(|c| format!("{c:?}"));
+            seen.dedup();
+            seen
+        };
+        assert!(distinct(ColorDepth::Truecolor).len() > 256);
+        let ansi256 = distinct(ColorDepth::Ansi256);
+        assert!(ansi256.len() <= 256 && ansi256.iter().all(|c| matches!(c, Color::AnsiValue(_))));
+        let ansi16 = distinct(ColorDepth::Ansi16);
+        assert!(ansi16.len() <= 16 && ansi16.iter().all(|c| matches!(c, Color::AnsiValue(0..=15))));
+        assert_eq!(distinct(ColorDepth::Mono), vec![Color::Reset]);
+    }
+
+    #[test]
+    fn neutral_contrast_is_identity_and_high_contrast_spreads() {
+        for x in [0.0, 0.1, 0.45, 0.5, 0.9, 1.0] {
+            assert!((adjust_intensity(x, 1.0, 0.0) - x).abs() < 1e-6);
+        }
+        assert!(adjust_intensity(0.4, 3.0, 0.0) < 0.4 && adjust_intensity(0.6, 3.0, 0.0) > 0.6);
+        assert_eq!(adjust_intensity(0.2, 10.0, 0.0), 0.0);
+        assert_eq!(adjust_intensity(0.8, 10.0, 0.0), 1.0);
+        assert_eq!(adjust_intensity(0.5, 10.0, 0.0), 0.5);
+    }
+
+    #[test]
+    fn full_hue_cycle_restores_the_palette() {
+        let near = |a: Color, b: Color| {
+            let ((r1, g1, b1), (r2, g2, b2)) = (to_rgb(a), to_rgb(b));
+            [r1.abs_diff(r2), g1.abs_diff(g2), b1.abs_diff(b2)].iter().all(|&d| d <= 1)
+        };
+        for theme in Theme::ALL {
+            let stops = theme.gradient();
+            for (turned, &(t, c)) in theme.hue_rotated(360.0).into_iter().zip(stops) {
+                assert_eq!(turned.0, t);
+                assert!(near(turned.1, c), "{theme:?}: {:?} vs {c:?}", turned.1);
+            }
+            // and in three turns of a third each
+            for &(_, c) in stops {
+                let thirds = rotate_hue(rotate_hue(rotate_hue(c, 120.0), 120.0), 120.0);
+                assert!(near(thirds, c), "{theme:?}: {thirds:?} vs {c:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn fog_starts_clear_and_thickens_with_distance() {
+        assert_eq!(fog_factor(0.3, 0.0), 0.0);
+        let fog: Vec<f32> = (0..50).map(|k| fog_factor(0.3, k as f32 * 0.5)).collect();
+        assert!(fog.windows(2).all(|w| w[0] < w[1] && w[1] < 1.0));
+        assert!(fog_factor(0.3, 100.0) > 0.999);
+        assert_eq!(fog_factor(0.0, 50.0), 0.0);
+    }
+
+    #[test]
+    fn normals_map_to_their_rgb_colors() {
+        assert_eq!(normal_color(Vec3::new(0.0, 0.0, 1.0)), Color::Rgb { r: 128, g: 128, b: 255 });
+        assert_eq!(normal_color(Vec3::new(-1.0, 0.0, 0.0)), Color::Rgb { r: 0, g: 128, b: 128 });
+    }
+
+    #[test]
+    fn oklab_midpoint_of_black_and_white_is_half_as_light() {
+        let (black, white) = (Color::Rgb { r: 0, g: 0, b: 0 }, Color::Rgb { r: 255, g: 255, b: 255 });
+        let (r, g, b) = to_rgb(lerp_color_oklab(&black, &white, 0.5));
+        assert!(r == g && g == b, "{r} {g} {b}: a gray");
+        let (l, _, _) = rgb_to_oklab(r, g, b);
+        assert!((l - 0.5).abs() < 0.01, "{l}");
+        // an even blend in sRGB is well lighter than that
+        let (r, g, b) = to_rgb(lerp_color(&black, &white, 0.5));
+        assert!(rgb_to_oklab(r, g, b).0 > 0.55);
+    }
+
+    #[test]
+    fn radial_map_puts_the_palette_ends_at_center_and_corners() {
+        let at = |x, y| sample_gradient(GRADIENT, ColorMap::Radial.coordinate(x, y).unwrap());
+        let (first, last) = (GRADIENT[0].1, GRADIENT[GRADIENT.len() - 1].1);
+        assert_eq!(at(0.5, 0.5), first);
+        for (x, y) in [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)] {
+            assert_eq!(at(x, y), last, "corner ({x}, {y})");
+        }
+        // the middle of an edge is 1/sqrt(2) of the way out to a corner
+        let edge = ColorMap::Radial.coordinate(0.5, 0.0).unwrap();
+        assert!((edge - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-6);
+        assert_eq!(ColorMap::Light.coordinate(0.5, 0.5), None);
+    }
+
+    #[test]
+    fn lut_matches_the_direct_palette_lookup() {
+        let lut = Lut::new(get_color_from_intensity);
+        let last = (LUT_SIZE - 1) as f32;
+        for k in 0..LUT_SIZE {
+            // exact on the samples, and within rounding halfway to the next
+            let at = k as f32 / last;
+            assert_eq!(lut.get(at), get_color_from_intensity(at));
+            let between = (k as f32 + 0.49) / last;
+            let (a, b) = (to_rgb(lut.get(between)), to_rgb(get_color_from_intensity(between)));
+            let close = |x: u8, y: u8| x.abs_diff(y) <= 2;
+            assert!(close(a.0, b.0) && close(a.1, b.1) && close(a.2, b.2), "{between}: {a:?} vs {b:?}");
+        }
+        assert_eq!(lut.get(-1.0), get_color_from_intensity(0.0));
+        assert_eq!(lut.get(2.0), get_color_from_intensity(1.0));
+    }
+}
@@ -0,0 +1,75 @@
+use crate::config::FLAGS;
+
+/// Shells `--generate-completions` can write a script for.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl std::str::FromStr for Shell {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            _ => Err(format!("unknown shell: {s} (expected bash, zsh or fish)")),
+        }
+    }
+}
+
+/// Completion script for `bin` that offers every flag in `FLAGS`.
+pub fn script(shell: Shell, bin: &str) -> String {
+    let mut out = String::new();
+    match shell {
+        Shell::Bash => {
+            let words: Vec<&str> = FLAGS.iter().map(|&(flag, _)| flag).collect();
+            let with_value: Vec<&str> =
+                FLAGS.iter().filter(|&&(_, value)| value).map(|&(flag, _)| flag).collect();
+            out += &format!("_{bin}() {{\n");
+            out += "    local cur=\"${COMP_WORDS[COMP_CWORD]}\" prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n";
+            // after a flag that takes a value, fall back to file names
+            out += &format!("    case \"$prev\" in\n        {})\n", with_value.join("|"));
+            out += "            COMPREPLY=($(compgen -f -- \"$cur\")); return;;\n    esac\n";
+            out += &format!("    COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n}}\n", words.join(" "));
+            out += &format!("complete -F _{bin} {bin}\n");
+        }
+        Shell::Zsh => {
+            out += &format!("#compdef {bin}\n\n_arguments \\\n");
+            for &(flag, value) in FLAGS {
+                let spec = if value { format!("{flag}:value:_files") } else { flag.to_string() };
+                out += &format!("    '{spec}' \\\n");
+            }
+            out.truncate(out.len() - 3);
+            out += "\n";
+        }
+        Shell::Fish => {
+            for &(flag, value) in FLAGS {
+                let long = flag.trim_start_matches("--");
+                out += &format!("complete -c {bin} -l {long}{}\n", if value { " -r" } else { "" });
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bash_script_offers_the_known_flags() {
+        let out = script(Shell::Bash, "torus");
+        assert!(out.contains("complete -F _torus torus"));
+        // value flags fall back to file names, the rest are plain words
+        assert!(out.contains("--seed|") || out.contains("|--seed"));
+        for &(flag, _) in FLAGS {
+            assert!(out.contains(flag), "missing {flag}");
+        }
+    }
+}
@@ -0,0 +1,734 @@
+use crossterm::style::Color;
+
+use crate::camera::Spin;
+use crate::color::{parse_hex, ColorDepth, ColorMap, Interpolation, Theme, Tonemap};
+use crate::completions::Shell;
+use crate::error::DonutError;
+use crate::light::{Lighting, SpecularModel};
+use crate::math::Vec3;
+use crate::output::{LineEnding, Screen};
+use crate::palette::load_palette;
+use crate::pattern::Pattern;
+
+/// Every public flag and whether it takes a value, for shell completions.
+/// The tests check it against `Config::from_args` and the README.
+pub const FLAGS: &[(&str, bool)] = &[
+    ("--frames", true),
+    ("--ping-pong", true),
+    ("--start-frame", true),
+    ("--end-frame", true),
+    ("--chain", true),
+    ("--twin", false),
+    ("--knot", true),
+    ("--ripple", true),
+    ("--pattern", true),
+    ("--pattern-scale", true),
+    ("--stars", true),
+    ("--twinkle", false),
+    ("--background", true),
+    ("--background-color", true),
+    ("--bloom", true),
+    ("--bloom-radius", true),
+    ("--aa", false),
+    ("--antialias-edges-only", false),
+    ("--taa", false),
+    ("--outline", false),
+    ("--accumulate", true),
+    ("--focus", true),
+    ("--aperture", true),
+    ("--morph", true),
+    ("--floor", true),
+    ("--shadows", true),
+    ("--fog", true),
+    ("--fog-color", true),
+    ("--palette", true),
+    ("--clip", true),
+    ("--cap-color", true),
+    ("--hole-color", true),
+    ("--banner", true),
+    ("--banner-scroll", true),
+    ("--text3d", true),
+    ("--text3d-union", false),
+    ("--theme", true),
+    ("--subsample", true),
+    ("--budget-ms", true),
+    ("--progressive", false),
+    ("--min-step", true),
+    ("--base-eps", true),
+    ("--normal-offset", true),
+    ("--specular-model", true),
+    ("--shininess", true),
+    ("--specular-color", true),
+    ("--max-bounces", true),
+    ("--reflectivity", true),
+    ("--ior", true),
+    ("--transmission", true),
+    ("--spin", true),
+    ("--shake", true),
+    ("--shake-frequency", true),
+    ("--shake-duration", true),
+    ("--lighting", true),
+    ("--headlamp", false),
+    ("--light-orbit", true),
+    ("--light-orbit-axis", true),
+    ("--wrap", true),
+    ("--list-themes", false),
+    ("--edit-palette", false),
+    ("--dump-config", false),
+    ("--jitter", false),
+    ("--interpolation", true),
+    ("--color-map", true),
+    ("--color-map-mix", true),
+    ("--tonemap", true),
+    ("--contrast", true),
+    ("--brightness", true),
+    ("--color-cycle", true),
+    ("--seed", true),
+    ("--screensaver", false),
+    ("--demo", false),
+    ("--ascii-only", false),
+    ("--sixel", true),
+    ("--ansi256", false),
+    ("--dither", false),
+    ("--preview-palette", true),
+    ("--no-alt-screen", false),
+    ("--persist", false),
+    ("--frame", true),
+    ("--headless", false),
+    ("--line-ending", true),
+    ("--ppm", true),
+    ("--size", true),
+    ("--canvas", true),
+    ("--max-width", true),
+    ("--max-height", true),
+    ("--lock-aspect", false),
+    ("--fit", true),
+    ("--flat", false),
+    ("--normals", false),
+    ("--uv-view", false),
+    ("--bench", true),
+    ("--warmup", true),
+    ("--checksum", false),
+    ("--pipe-rgb", false),
+    ("--stats", true),
+    ("--profile", false),
+    ("--march-log", true),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Config {
+    // number of frames to render, 0 runs until the user quits
+    pub frames: u32,
+    // play only frames start_frame..end_frame of the animation, each posed
+    // as in a full run; end_frame overrides frames
+    pub start_frame: u32,
+    pub end_frame: Option<u32>,
+    // play the animation forward for this many frames, then back, looping
+    pub ping_pong: Option<u32>,
+    // number of links for the chain scene; None renders the single donut
+    pub chain: Option<usize>,
+    // add a second donut spinning inside the first
+    pub twin: bool,
+    // (p, q) torus knot instead of the donut: p turns around, q windings
+    pub knot: Option<(u32, u32)>,
+    // amplitude of the animated surface ripple, 0 disables it
+    pub ripple: f32,
+    pub pattern: Pattern,
+    // pattern periods around each circle of the torus
+    pub pattern_scale: f32,
+    // fraction of background cells showing a star
+    pub stars: f32,
+    pub twinkle: bool,
+    // glyph and color for empty background cells instead of a blank
+    pub background: Option<char>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::hex"))]
+    pub background_color: Color,
+    // glow onto the background around cells at least this bright (0..1)
+    pub bloom: Option<f32>,
+    // reach of the glow in cells
+    pub bloom_radius: usize,
+    // blend silhouette cells by their estimated coverage
+    pub aa: bool,
+    // supersample only the cells either side of a silhouette
+    pub antialias_edges_only: bool,
+    // temporal anti-aliasing: jitter rays within their cell every frame
+    // and blend into the frames before
+    pub taa: bool,
+    // draw a cartoon outline along silhouettes and creases
+    pub outline: bool,
+    // blend each frame into the running image by this much, for trails
+    pub accumulate: Option<f32>,
+    // depth of field: blur hits by their distance from this focus plane
+    pub focus: Option<f32>,
+    // lens opening for --focus; larger blurs more away from the focus plane
+    pub aperture: f32,
+    // melt into a sphere and back at this many degrees of phase per frame
+    pub morph: Option<f32>,
+    // checkerboard floor under the scene with squares of this size
+    pub floor: Option<f32>,
+    // soft shadows on the floor, penumbra widening by this per unit of
+    // distance from the occluder
+    pub shadows: Option<f32>,
+    // hits fade toward fog_color with distance, 0 disables it
+    pub fog_density: f32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::hex"))]
+    pub fog_color: Color,
+    // gradient stops read from a --palette file, replacing the theme's
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub palette: Option<Vec<(f32, Color)>>,
+    // cut the near side away with a plane sweeping at this many degrees of
+    // phase per frame, 0 holds it through the center
+    pub clip: Option<f32>,
+    // color of the surface the clipping plane cuts open
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::hex"))]
+    pub cap_color: Color,
+    // backdrop seen through the donut's hole, instead of the background
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::hex::option"))]
+    pub hole_color: Option<Color>,
+    // block-letter message drawn over the background
+    pub banner: Option<String>,
+    // the string extruded into 3D boxes, replacing the donut
+    pub text3d: Option<String>,
+    // keep the donut as well, in the same scene as the text
+    pub text3d_union: bool,
+    // banner scroll speed in cells per frame, 0 keeps it centered
+    pub banner_scroll: f32,
+    pub theme: Theme,
+    // march every nth cell in both directions, replicating the rest
+    pub subsample: usize,
+    // raise or lower subsample to keep each frame's render under this
+    pub budget_ms: Option<f32>,
+    // render coarse while keys or the mouse are in use, then sharpen
+    pub progressive: bool,
+    // sphere trace with this minimum step instead of fixed steps
+    pub min_step: Option<f32>,
+    // hit threshold proportional to the distance along the ray
+    pub base_eps: Option<f32>,
+    // take hit normals this far back along the ray, toward the camera and
+    // so just outside the surface, where convex edges are rounded off
+    pub normal_offset: f32,
+    // add specular highlights computed this way; None is diffuse only
+    pub specular_model: Option<SpecularModel>,
+    // highlight exponent; larger is smaller and sharper
+    pub shininess: f32,
+    // add highlights in this color on top of the diffuse one instead of
+    // brightening it
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::hex::option"))]
+    pub specular_color: Option<Color>,
+    // mirror bounces followed from every hit, 0 is direct light only
+    pub max_bounces: u32,
+    // share of a surface's light that comes from its mirror bounce
+    pub reflectivity: f32,
+    // glassy surface: refract with this index and see what is behind
+    pub ior: Option<f32>,
+    // share of the refracted light with --ior, 0..1
+    pub transmission: f32,
+    // turn the scene, or orbit the camera around it instead
+    pub spin: Spin,
+    // jolt the camera by up to this much at the start, fading out over
+    // shake_duration frames
+    pub shake: Option<f32>,
+    // new shake offsets per frame; below 1 they drift, above they rattle
+    pub shake_frequency: f32,
+    pub shake_duration: u32,
+    // light rig; None keeps the single light from the upper left
+    pub lighting: Option<Lighting>,
+    // keep the lights fixed relative to the camera instead of the world
+    pub headlamp: bool,
+    // swing the lights around light_orbit_axis by this many degrees per frame
+    pub light_orbit: Option<f32>,
+    pub light_orbit_axis: Vec3,
+    // half-Lambert wrap of the diffuse falloff, 0 is plain Lambert
+    pub wrap: f32,
+    // per-frame noise on the glyph choice against banding
+    pub jitter: bool,
+    // color space the palette stops are blended in
+    pub interpolation: Interpolation,
+    // take the palette color from screen position as well as lighting,
+    // weighted by color_map_mix (1 is position only)
+    pub color_map: ColorMap,
+    pub color_map_mix: f32,
+    // curve that fits intensities over 1 (highlights) into the palette
+    pub tonemap: Tonemap,
+    // applied to the lighting intensity before color mapping
+    pub contrast: f32,
+    pub brightness: f32,
+    // palette hue rotation in degrees per frame
+    pub color_cycle: f32,
+    // seeds every randomized feature so renders are reproducible; the
+    // binary picks and prints a time-based seed when it's missing
+    pub seed: Option<u64>,
+    // randomized blob scene that periodically re-rolls itself
+    pub screensaver: bool,
+    // scripted reel through a few features, with captions
+    pub demo: bool,
+    // emit glyphs only, without any color escapes
+    pub ascii_only: bool,
+    // draw the colors as a Sixel image, this many pixels square per cell
+    pub sixel: Option<u16>,
+    // map colors to the nearest of the 256-color palette
+    pub ansi256: bool,
+    // like ansi256, with Floyd–Steinberg error diffusion
+    pub dither: bool,
+    // restrict the output to the colors a lesser terminal has
+    pub preview_palette: ColorDepth,
+    // draw inline instead of on the alternate screen
+    pub inline: bool,
+    // copy the last frame into the normal screen on exit
+    pub persist: bool,
+    // render only this frame index, then exit
+    pub frame: Option<u32>,
+    // print the still frame as plain text to stdout instead of drawing it
+    pub headless: bool,
+    // what ends each row of the --headless text
+    pub line_ending: LineEnding,
+    // write the still frame to this PPM file
+    pub ppm: Option<String>,
+    // render size for headless and file output; defaults to 80x24
+    pub size: Option<(u16, u16)>,
+    // fixed live render size, scaled to fit the terminal
+    pub canvas: Option<(u16, u16)>,
+    // cap on the live render size; a larger terminal letterboxes it
+    pub max_width: Option<u16>,
+    pub max_height: Option<u16>,
+    // render a square viewport and box the rest of the terminal
+    pub lock_aspect: bool,
+    // place the camera so the scene's bounding sphere fills this share of
+    // the view, instead of the fixed distance
+    pub fit: Option<f32>,
+    // faceted shading from neighbouring hit points instead of SDF normals
+    pub flat: bool,
+    // color hits by their surface normal instead of shading them
+    pub normals: bool,
+    // debug view: unwrap the visible surface onto a flat (u, v) rectangle
+    pub uv_view: bool,
+    // print the available themes and exit
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub list_themes: bool,
+    // tune the gradient live with the number and arrow keys
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub edit_palette: bool,
+    // print the resolved config as TOML and exit
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub dump_config: bool,
+    // render flat out for this many seconds, print the frame rate and exit
+    pub bench: Option<f32>,
+    // frames rendered before --bench and --stats start counting
+    pub warmup: u32,
+    // print a hash of every frame instead of drawing it
+    pub checksum: bool,
+    // stream raw RGB24 frames to stdout instead of drawing them
+    pub pipe_rgb: bool,
+    // append per-frame render statistics to this JSON lines file
+    pub stats: Option<String>,
+    // time the render stages and the terminal write, printed on exit
+    pub profile: bool,
+    // print every march step of the ray through this column of the middle
+    // row, once, to stderr
+    pub march_log: Option<u16>,
+    // print a completion script for this shell and exit
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub completions: Option<Shell>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            frames: 20_000,
+            start_frame: 0,
+            end_frame: None,
+            ping_pong: None,
+            chain: None,
+            twin: false,
+            knot: None,
+            ripple: 0.0,
+            pattern: Pattern::Solid,
+            pattern_scale: 8.0,
+            stars: 0.0,
+            twinkle: false,
+            background: None,
+            background_color: Color::Rgb { r: 60, g: 60, b: 60 },
+            bloom: None,
+            bloom_radius: 1,
+            aa: false,
+            antialias_edges_only: false,
+            taa: false,
+            outline: false,
+            accumulate: None,
+            focus: None,
+            aperture: 1.0,
+            morph: None,
+            floor: None,
+            shadows: None,
+            fog_density: 0.0,
+            fog_color: Color::Rgb { r: 0, g: 0, b: 0 },
+            palette: None,
+            clip: None,
+            cap_color: Color::Rgb { r: 255, g: 80, b: 120 },
+            hole_color: None,
+            banner: None,
+            text3d: None,
+            text3d_union: false,
+            banner_scroll: 0.0,
+            theme: Theme::Temperature,
+            subsample: 1,
+            budget_ms: None,
+            progressive: false,
+            min_step: None,
+            base_eps: None,
+            normal_offset: 0.0,
+            specular_model: None,
+            shininess: 32.0,
+            specular_color: None,
+            max_bounces: 0,
+            reflectivity: 0.3,
+            ior: None,
+            transmission: 0.5,
+            spin: Spin::Shape,
+            shake: None,
+            shake_frequency: 0.5,
+            shake_duration: 90,
+            lighting: None,
+            headlamp: false,
+            light_orbit: None,
+            // the screen's vertical, so the light circles around the sides
+            light_orbit_axis: Vec3::new(0.0, 0.0, 1.0),
+            wrap: 0.0,
+            jitter: false,
+            interpolation: Interpolation::Rgb,
+            color_map: ColorMap::Light,
+            color_map_mix: 0.5,
+            tonemap: Tonemap::None,
+            contrast: 1.0,
+            brightness: 0.0,
+            color_cycle: 0.0,
+            seed: None,
+            screensaver: false,
+            demo: false,
+            ascii_only: false,
+            sixel: None,
+            ansi256: false,
+            dither: false,
+            preview_palette: ColorDepth::Truecolor,
+            inline: false,
+            persist: false,
+            frame: None,
+            headless: false,
+            line_ending: LineEnding::Lf,
+            ppm: None,
+            size: None,
+            canvas: None,
+            max_width: None,
+            max_height: None,
+            lock_aspect: false,
+            fit: None,
+            flat: false,
+            normals: false,
+            uv_view: false,
+            list_themes: false,
+            edit_palette: false,
+            dump_config: false,
+            bench: None,
+            warmup: 0,
+            checksum: false,
+            pipe_rgb: false,
+            stats: None,
+            profile: false,
+            march_log: None,
+            completions: None,
+        }
+    }
+}
+
+impl Config {
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Self, DonutError> {
+        let mut cfg = Config::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--frames" => cfg.frames = parse_value(&arg, args.next())?,
+                "--start-frame" => cfg.start_frame = parse_value(&arg, args.next())?,
+                "--end-frame" => cfg.end_frame = Some(parse_value(&arg, args.next())?),
+                "--ping-pong" => cfg.ping_pong = Some(parse_value(&arg, args.next())?),
+                "--chain" => cfg.chain = Some(parse_value(&arg, args.next())?),
+                "--twin" => cfg.twin = true,
+                "--knot" => {
+                    let p = parse_value(&arg, args.next())?;
+                    cfg.knot = Some((p, parse_value(&arg, args.next())?));
+                }
+                "--ripple" => cfg.ripple = parse_value(&arg, args.next())?,
+                "--pattern" => cfg.pattern = parse_value(&arg, args.next())?,
+                "--pattern-scale" => cfg.pattern_scale = parse_value(&arg, args.next())?,
+                "--stars" => cfg.stars = parse_value(&arg, args.next())?,
+                "--twinkle" => cfg.twinkle = true,
+                "--background" => cfg.background = Some(parse_value(&arg, args.next())?),
+                "--background-color" => {
+                    cfg.background_color = parse_hex(&parse_value::<String>(&arg, args.next())?)?
+                }
+                "--bloom" => cfg.bloom = Some(parse_value(&arg, args.next())?),
+                "--bloom-radius" => cfg.bloom_radius = parse_value(&arg, args.next())?,
+                "--aa" => cfg.aa = true,
+                "--antialias-edges-only" => cfg.antialias_edges_only = true,
+                "--taa" => cfg.taa = true,
+                "--outline" => cfg.outline = true,
+                "--accumulate" => cfg.accumulate = Some(parse_value(&arg, args.next())?),
+                "--focus" => cfg.focus = Some(parse_value(&arg, args.next())?),
+                "--aperture" => cfg.aperture = parse_value(&arg, args.next())?,
+                "--morph" => cfg.morph = Some(parse_value(&arg, args.next())?),
+                "--floor" => cfg.floor = Some(parse_value(&arg, args.next())?),
+                "--shadows" => cfg.shadows = Some(parse_value(&arg, args.next())?),
+                "--fog" => cfg.fog_density = parse_value(&arg, args.next())?,
+                "--fog-color" => cfg.fog_color = parse_hex(&parse_value::<String>(&arg, args.next())?)?,
+                "--palette" => cfg.palette = Some(load_palette(&parse_value::<String>(&arg, args.next())?)?),
+                "--clip" => cfg.clip = Some(parse_value(&arg, args.next())?),
+                "--cap-color" => cfg.cap_color = parse_hex(&parse_value::<String>(&arg, args.next())?)?,
+                "--hole-color" => {
+                    cfg.hole_color = Some(parse_hex(&parse_value::<String>(&arg, args.next())?)?)
+                }
+                "--banner" => cfg.banner = Some(parse_value(&arg, args.next())?),
+                "--text3d" => cfg.text3d = Some(parse_value(&arg, args.next())?),
+                "--text3d-union" => cfg.text3d_union = true,
+                "--banner-scroll" => cfg.banner_scroll = parse_value(&arg, args.next())?,
+                "--theme" => cfg.theme = parse_value(&arg, args.next())?,
+                "--subsample" => cfg.subsample = parse_value(&arg, args.next())?,
+                "--budget-ms" => cfg.budget_ms = Some(parse_value(&arg, args.next())?),
+                "--progressive" => cfg.progressive = true,
+                "--min-step" => cfg.min_step = Some(parse_value(&arg, args.next())?),
+                "--base-eps" => cfg.base_eps = Some(parse_value(&arg, args.next())?),
+                "--normal-offset" => cfg.normal_offset = parse_value(&arg, args.next())?,
+                "--specular-model" => cfg.specular_model = Some(parse_value(&arg, args.next())?),
+                "--shininess" => cfg.shininess = parse_value(&arg, args.next())?,
+                "--specular-color" => {
+                    cfg.specular_color = Some(parse_hex(&parse_value::<String>(&arg, args.next())?)?)
+                }
+                "--max-bounces" => cfg.max_bounces = parse_value(&arg, args.next())?,
+                "--reflectivity" => cfg.reflectivity = parse_value(&arg, args.next())?,
+                "--ior" => cfg.ior = Some(parse_value(&arg, args.next())?),
+                "--transmission" => cfg.transmission = parse_value(&arg, args.next())?,
+                "--spin" => cfg.spin = parse_value(&arg, args.next())?,
+                "--shake" => cfg.shake = Some(parse_value(&arg, args.next())?),
+                "--shake-frequency" => cfg.shake_frequency = parse_value(&arg, args.next())?,
+                "--shake-duration" => cfg.shake_duration = parse_value(&arg, args.next())?,
+                "--lighting" => cfg.lighting = Some(parse_value(&arg, args.next())?),
+                "--headlamp" => cfg.headlamp = true,
+                "--light-orbit" => cfg.light_orbit = Some(parse_value(&arg, args.next())?),
+                "--light-orbit-axis" => cfg.light_orbit_axis = parse_vec3(&arg, args.next())?,
+                "--wrap" => cfg.wrap = parse_value(&arg, args.next())?,
+                "--list-themes" => cfg.list_themes = true,
+                "--edit-palette" => cfg.edit_palette = true,
+                "--dump-config" => cfg.dump_config = true,
+                "--jitter" => cfg.jitter = true,
+                "--interpolation" => cfg.interpolation = parse_value(&arg, args.next())?,
+                "--color-map" => cfg.color_map = parse_value(&arg, args.next())?,
+                "--color-map-mix" => cfg.color_map_mix = parse_value(&arg, args.next())?,
+                "--tonemap" => cfg.tonemap = parse_value(&arg, args.next())?,
+                "--contrast" => cfg.contrast = parse_value(&arg, args.next())?,
+                "--brightness" => cfg.brightness = parse_value(&arg, args.next())?,
+                "--color-cycle" => cfg.color_cycle = parse_value(&arg, args.next())?,
+                "--seed" => cfg.seed = Some(parse_value(&arg, args.next())?),
+                "--screensaver" => cfg.screensaver = true,
+                "--demo" => cfg.demo = true,
+                "--ascii-only" => cfg.ascii_only = true,
+                "--sixel" => cfg.sixel = Some(parse_value(&arg, args.next())?),
+                "--ansi256" => cfg.ansi256 = true,
+                "--dither" => cfg.dither = true,
+                "--preview-palette" => cfg.preview_palette = parse_value(&arg, args.next())?,
+                "--no-alt-screen" => cfg.inline = true,
+                "--persist" => cfg.persist = true,
+                "--frame" => cfg.frame = Some(parse_value(&arg, args.next())?),
+                "--headless" => cfg.headless = true,
+                "--line-ending" => cfg.line_ending = parse_value(&arg, args.next())?,
+                "--ppm" => cfg.ppm = Some(parse_value(&arg, args.next())?),
+                "--size" => cfg.size = Some(parse_size(&arg, args.next())?),
+                "--canvas" => cfg.canvas = Some(parse_size(&arg, args.next())?),
+                "--max-width" => cfg.max_width = Some(parse_value(&arg, args.next())?),
+                "--max-height" => cfg.max_height = Some(parse_value(&arg, args.next())?),
+                "--lock-aspect" => cfg.lock_aspect = true,
+                "--fit" => cfg.fit = Some(parse_value(&arg, args.next())?),
+                "--flat" => cfg.flat = true,
+                "--normals" => cfg.normals = true,
+                "--uv-view" => cfg.uv_view = true,
+                "--bench" => cfg.bench = Some(parse_value(&arg, args.next())?),
+                "--warmup" => cfg.warmup = parse_value(&arg, args.next())?,
+                "--checksum" => cfg.checksum = true,
+                "--pipe-rgb" => cfg.pipe_rgb = true,
+                "--profile" => cfg.profile = true,
+                "--march-log" => cfg.march_log = Some(parse_value(&arg, args.next())?),
+                "--stats" => cfg.stats = Some(parse_value(&arg, args.next())?),
+                // hidden: not in FLAGS or the README
+                "--generate-completions" => {
+                    cfg.completions = Some(parse_value(&arg, args.next())?)
+                }
+                _ => return Err(DonutError::Config(format!("unknown argument: {arg}"))),
+            }
+        }
+        if cfg.ascii_only && cfg.sixel.is_some() {
+            // a sixel image is nothing but color
+            return Err(DonutError::Config("--ascii-only and --sixel can't be combined".into()));
+        }
+        Ok(cfg)
+    }
+
+    /// The seed for randomized features; 0 when none was given.
+    pub fn seed(&self) -> u64 {
+        self.seed.unwrap_or_default()
+    }
+
+    /// Whether frame `t` of a run that started at frame `start` counts toward
+    /// `--bench` and `--stats`: the first `warmup` frames are rendered but
+    /// left out.
+    pub fn counts_frame(&self, t: u32, start: u32) -> bool {
+        t.saturating_sub(start) >= self.warmup
+    }
+
+    /// The config as TOML, the format `--dump-config` prints.
+    #[cfg(feature = "serde")]
+    pub fn to_toml(&self) -> Result<String, DonutError> {
+        toml::to_string(self).map_err(|e| DonutError::Config(e.to_string()))
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn from_toml(s: &str) -> Result<Self, DonutError> {
+        toml::from_str(s).map_err(|e| DonutError::Config(e.to_string()))
+    }
+
+    pub fn screen(&self) -> Screen {
+        if self.inline { Screen::Inline } else { Screen::Alternate }
+    }
+
+    /// Frame indices to render: exactly `frames` of them, or for 0 a range
+    /// that is unbounded in practice (about two years at 60 fps), cut down to
+    /// `start_frame..end_frame`.
+    pub fn frame_range(&self) -> std::ops::Range<u32> {
+        let end = match (self.end_frame, self.frames) {
+            (Some(end), _) => end,
+            (None, 0) => u32::MAX,
+            (None, n) => n,
+        };
+        self.start_frame.min(end)..end
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("{flag} expects a value"))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid value for {flag}: {value}"))
+}
+
+/// Parses a direction `X,Y,Z`, e.g. `0,0,1`, normalized.
+fn parse_vec3(flag: &str, value: Option<String>) -> Result<Vec3, String> {
+    let value = value.ok_or_else(|| format!("{flag} expects a value"))?;
+    let parts: Vec<f32> = value.split(',').map_while(|p| p.trim().parse().ok()).collect();
+    match parts[..] {
+        [x, y, z] if x != 0.0 || y != 0.0 || z != 0.0 => Ok(Vec3::new(x, y, z).norm()),
+        _ => Err(format!("invalid value for {flag}: {value} (expected X,Y,Z)")),
+    }
+}
+
+/// Parses `WxH`, e.g. `80x24`.
+fn parse_size(flag: &str, value: Option<String>) -> Result<(u16, u16), String> {
+    let value = value.ok_or_else(|| format!("{flag} expects a value"))?;
+    value
+        .split_once('x')
+        .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+        .filter(|&(w, h)| w > 0 && h > 0)
+        .ok_or_else(|| format!("invalid value for {flag}: {value} (expected WxH)"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Config, DonutError> {
+        Config::from_args(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn every_listed_flag_parses_as_listed() {
+        for &(flag, value) in FLAGS {
+            match parse(&[flag]) {
+                Ok(_) => assert!(!value, "{flag} is listed as taking a value"),
+                Err(e) => {
+                    assert!(value, "{flag} is listed as a switch but failed: {e}");
+                    assert_eq!(e.to_string(), format!("{flag} expects a value"));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn every_listed_flag_is_recognized() {
+        for &(flag, value) in FLAGS {
+            // a dummy value may well be rejected, but not the flag itself
+            let args: &[&str] = if value { &[flag, "1"] } else { &[flag] };
+            if let Err(e) = parse(args) {
+                assert!(!e.to_string().starts_with("unknown argument"), "{flag}: {e}");
+            }
+        }
+        assert!(parse(&["--no-such-flag"]).unwrap_err().to_string().starts_with("unknown argument"));
+    }
+
+    #[test]
+    fn every_listed_flag_is_documented() {
+        let readme = include_str!("../README.md");
+        for &(flag, _) in FLAGS {
+            assert!(readme.contains(&format!("`{flag}")), "{flag} is missing from the README");
+        }
+    }
+
+    #[test]
+    fn zero_frames_loops_and_n_frames_stops() {
+        let range = |frames: u32| Config { frames, ..Config::default() }.frame_range();
+        assert_eq!(range(0), 0..u32::MAX);
+        assert_eq!(range(0).take(100_000).count(), 100_000);
+        assert_eq!(range(5).count(), 5);
+        assert_eq!(range(1).count(), 1);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn config_round_trips_through_toml() {
+        let default = Config::default();
+        assert_eq!(Config::from_toml(&default.to_toml().unwrap()).unwrap(), default);
+        let args = [
+            "--theme", "neon", "--fog-color", "#102030", "--specular-color", "#ff8000", "--chain", "3",
+            "--knot", "2", "3", "--light-orbit-axis", "1,2,3", "--spin", "camera", "--seed", "9",
+        ];
+        let custom = parse(&args).unwrap();
+        let toml = custom.to_toml().unwrap();
+        assert!(toml.contains("\"#ff8000\""));
+        assert_eq!(Config::from_toml(&toml).unwrap(), custom);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn dumped_default_config_reloads_equal() {
+        // what `torus --dump-config` prints with nothing else given
+        let resolved = parse(&["--dump-config"]).unwrap();
+        let dump = resolved.to_toml().unwrap();
+        let reloaded = Config::from_toml(&dump).unwrap();
+        // the flag itself isn't saved, so loading the dump doesn't dump again
+        assert_eq!(reloaded, Config::default());
+        assert_eq!(reloaded.to_toml().unwrap(), dump);
+    }
+
+    #[test]
+    fn warmup_frames_are_not_counted() {
+        let cfg = parse(&["--warmup", "5"]).unwrap();
+        let counted: Vec<u32> = (10..30).filter(|&t| cfg.counts_frame(t, 10)).collect();
+        assert_eq!(counted, (15..30).collect::<Vec<_>>());
+        // a run shorter than the warmup counts nothing, no warmup counts all
+        assert_eq!((10..14).filter(|&t| cfg.counts_frame(t, 10)).count(), 0);
+        assert!((0..20).all(|t| Config::default().counts_frame(t, 0)));
+    }
+
+    #[test]
+    fn ascii_only_rules_out_sixel() {
+        let err = parse(&["--ascii-only", "--sixel", "8"]).unwrap_err();
+        assert_eq!(err.to_string(), "--ascii-only and --sixel can't be combined");
+        assert!(parse(&["--sixel", "8"]).is_ok() && parse(&["--ascii-only"]).is_ok());
+    }
+}
@@ -0,0 +1,135 @@
+//! Scripted showcase for `--demo`: one segment after another, each fading in
+//! and out with a caption, on top of the user's own settings.
+
+use crossterm::style::Color;
+
+use crate::color::{lerp_color, Theme};
+use crate::config::Config;
+use crate::render::{RenderMode, Renderer};
+
+// frames of fade from and to black at either end of every segment
+pub const FADE: u32 = 30;
+
+/// One part of the demo reel.
+#[derive(Copy, Clone, Debug)]
+pub struct Segment {
+    pub caption: &'static str,
+    pub frames: u32,
+    // what the segment changes in the config it starts from
+    pub setup: fn(&mut Config),
+    // debug view to show instead of the config's, like the m key picks
+    pub mode: Option<RenderMode>,
+}
+
+/// The reel, in playing order; about four seconds each at 60 fps.
+pub const SEGMENTS: [Segment; 8] = [
+    Segment { caption: "the classic donut", frames: 240, setup: |_| {}, mode: None },
+    Segment { caption: "--theme ocean", frames: 240, setup: |cfg| cfg.theme = Theme::Ocean, mode: None },
+    Segment { caption: "--chain 3", frames: 240, setup: |cfg| cfg.chain = Some(3), mode: None },
+    Segment { caption: "--twin", frames: 240, setup: |cfg| cfg.twin = true, mode: None },
+    Segment { caption: "--outline", frames: 240, setup: |cfg| cfg.outline = true, mode: None },
+    Segment { caption: "--normals", frames: 240, setup: |cfg| cfg.normals = true, mode: None },
+    Segment { caption: "depth (m key)", frames: 240, setup: |_| {}, mode: Some(RenderMode::Depth) },
+    Segment { caption: "--knot 2 3", frames: 240, setup: |cfg| cfg.knot = Some((2, 3)), mode: None },
+];
+
+/// Length of the whole reel in frames.
+pub fn total_frames() -> u32 {
+    SEGMENTS.iter().map(|s| s.frames).sum()
+}
+
+/// Index of the segment playing at frame `t` of the reel and the frame
+/// within it; None past the end.
+pub fn segment_at(t: u32) -> Option<(usize, u32)> {
+    let mut start = 0;
+    for (k, segment) in SEGMENTS.iter().enumerate() {
+        if t < start + segment.frames {
+            return Some((k, t - start));
+        }
+        start += segment.frames;
+    }
+    None
+}
+
+/// `base` as segment `k` shows it.
+pub fn segment_config(base: &Config, k: usize) -> Config {
+    let mut cfg = base.clone();
+    (SEGMENTS[k].setup)(&mut cfg);
+    cfg
+}
+
+/// A fresh renderer for segment `k`, keeping what `previous` found out
+/// about the terminal and whether it already printed its --march-log.
+pub fn segment_renderer(base: &Config, k: usize, previous: &Renderer) -> Renderer {
+    let mut renderer = Renderer::new(&segment_config(base, k));
+    renderer.pixel_aspect = previous.pixel_aspect;
+    renderer.march_logged = previous.march_logged;
+    if let Some(mode) = SEGMENTS[k].mode {
+        renderer.mode = mode;
+    }
+    renderer
+}
+
+/// Brightness at frame `local` of a segment `frames` long: ramping up from
+/// black over the first `FADE` frames and back down over the last.
+pub fn fade(local: u32, frames: u32) -> f32 {
+    let edge = local.min(frames.saturating_sub(local + 1));
+    (edge as f32 / FADE as f32).min(1.0)
+}
+
+/// Dims every colored cell toward black by `brightness`.
+pub fn dim(colors: &mut [Color], brightness: f32) {
+    let black = Color::Rgb { r: 0, g: 0, b: 0 };
+    for c in colors.iter_mut().filter(|c| matches!(c, Color::Rgb { .. })) {
+        *c = lerp_color(&black, c, brightness);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reel_plays_the_segments_in_order() {
+        let captions: Vec<_> = SEGMENTS.iter().map(|s| s.caption).collect();
+        assert_eq!(
+            captions,
+            [
+                "the classic donut",
+                "--theme ocean",
+                "--chain 3",
+                "--twin",
+                "--outline",
+                "--normals",
+                "depth (m key)",
+                "--knot 2 3"
+            ]
+        );
+        assert!(SEGMENTS.iter().all(|s| s.frames == 240));
+        assert_eq!(total_frames(), 8 * 240);
+        assert_eq!(segment_at(0), Some((0, 0)));
+        assert_eq!(segment_at(239), Some((0, 239)));
+        assert_eq!(segment_at(240 * 6 + 5), Some((6, 5)));
+        assert_eq!(segment_at(total_frames()), None);
+    }
+
+    #[test]
+    fn segments_keep_the_terminal_setup_and_march_log() {
+        let mut previous = Renderer::new(&Config::default());
+        previous.pixel_aspect = 0.6;
+        previous.march_logged = true;
+        let depth = segment_renderer(&Config::default(), 6, &previous);
+        assert_eq!(depth.mode, RenderMode::Depth);
+        assert_eq!(depth.pixel_aspect, 0.6);
+        assert!(depth.march_logged);
+        assert_eq!(segment_renderer(&Config::default(), 5, &previous).mode, RenderMode::Normals);
+    }
+
+    #[test]
+    fn fades_from_and_to_black() {
+        assert_eq!(fade(0, 240), 0.0);
+        assert_eq!(fade(FADE, 240), 1.0);
+        assert_eq!(fade(120, 240), 1.0);
+        assert_eq!(fade(239, 240), 0.0);
+    }
+}
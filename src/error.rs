@@ -0,0 +1,47 @@
+use std::{fmt, io};
+
+/// What can go wrong around rendering, which itself can't fail.
+#[derive(Debug)]
+pub enum DonutError {
+    // setting up or restoring the terminal
+    Terminal(io::Error),
+    // bad command line flag or config file
+    Config(String),
+    // reading or writing a file or the output stream
+    Io(io::Error),
+    // a palette file line that isn't `threshold #RRGGBB`, counted from 1
+    PaletteParse { line: usize, message: String },
+}
+
+impl fmt::Display for DonutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DonutError::Terminal(e) => write!(f, "terminal: {e}"),
+            DonutError::Config(message) => write!(f, "{message}"),
+            DonutError::Io(e) => write!(f, "{e}"),
+            DonutError::PaletteParse { line, message } => write!(f, "palette line {line}: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for DonutError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DonutError::Terminal(e) | DonutError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for DonutError {
+    fn from(e: io::Error) -> Self {
+        DonutError::Io(e)
+    }
+}
+
+// the flag parsers report plain messages
+impl From<String> for DonutError {
+    fn from(message: String) -> Self {
+        DonutError::Config(message)
+    }
+}
@@ -0,0 +1,63 @@
+use crate::math::Vec3;
+
+/// Checkerboard floor: the horizontal plane `z = height` (+Z points down),
+/// tiled with squares of side `square` and fading out with distance.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Floor {
+    pub height: f32,
+    pub square: f32,
+    // fog density per unit of ray distance
+    pub fog: f32,
+}
+
+impl Floor {
+    pub const NORMAL: Vec3 = Vec3::new(0.0, 0.0, -1.0);
+
+    /// Ray distance to the plane, solved directly instead of marched; `None`
+    /// when the ray runs parallel to it or points away.
+    pub fn intersect(&self, ro: Vec3, rd: Vec3) -> Option<f32> {
+        let k = (self.height - ro.z) / rd.z;
+        (k > 0.0 && k.is_finite()).then_some(k)
+    }
+
+    /// Whether `p` lies on a light square; neighbours alternate and the
+    /// pattern repeats every two squares.
+    pub fn is_light(&self, p: Vec3) -> bool {
+        let cells = (p.x / self.square).floor() + (p.y / self.square).floor();
+        cells.rem_euclid(2.0) == 0.0
+    }
+
+    /// How much of the floor survives the fog at ray distance `k`: 1 at the
+    /// camera, toward 0 (the background) far away.
+    pub fn visibility(&self, k: f32) -> f32 {
+        (-self.fog * k).exp()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn neighbouring_squares_alternate_every_period() {
+        let floor = Floor { height: 1.0, square: 0.5, fog: 0.0 };
+        let at = |x: f32, y: f32| floor.is_light(Vec3::new(x, y, 1.0));
+        for (x, y) in [(0.1, 0.1), (0.7, -0.3), (-1.2, 2.4)] {
+            assert_ne!(at(x, y), at(x + 0.5, y));
+            assert_ne!(at(x, y), at(x, y - 0.5));
+            assert_eq!(at(x, y), at(x + 0.5, y + 0.5));
+            assert_eq!(at(x, y), at(x + 1.0, y));
+            assert_eq!(at(x, y), at(x - 3.0, y + 7.0));
+        }
+    }
+
+    #[test]
+    fn rays_meet_the_plane_only_going_toward_it() {
+        let floor = Floor { height: 1.0, square: 0.5, fog: 0.0 };
+        let ro = Vec3::new(0.0, 0.0, -1.0);
+        assert_eq!(floor.intersect(ro, Vec3::new(0.0, 0.0, 1.0)), Some(2.0));
+        assert_eq!(floor.intersect(ro, Vec3::new(0.0, 0.0, -1.0)), None);
+        assert_eq!(floor.intersect(ro, Vec3::new(1.0, 0.0, 0.0)), None);
+    }
+}
@@ -0,0 +1,101 @@
+//! Tiny 5x7 bitmap font for A–Z and 0–9.
+
+pub const GLYPH_WIDTH: i32 = 5;
+pub const GLYPH_HEIGHT: i32 = 7;
+// horizontal advance per character, including one column of spacing
+pub const ADVANCE: i32 = GLYPH_WIDTH + 1;
+
+// one row per entry, bit 4 is the leftmost column
+#[rustfmt::skip]
+const LETTERS: [[u8; 7]; 26] = [
+    [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001], // A
+    [0b11110, 0b10001, 0b10001, 0b11110, 0b10001, 0b10001, 0b11110], // B
+    [0b01110, 0b10001, 0b10000, 0b10000, 0b10000, 0b10001, 0b01110], // C
+    [0b11110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11110], // D
+    [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111], // E
+    [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b10000], // F
+    [0b01110, 0b10001, 0b10000, 0b10111, 0b10001, 0b10001, 0b01111], // G
+    [0b10001, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001], // H
+    [0b01110, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110], // I
+    [0b00111, 0b00010, 0b00010, 0b00010, 0b00010, 0b10010, 0b01100], // J
+    [0b10001, 0b10010, 0b10100, 0b11000, 0b10100, 0b10010, 0b10001], // K
+    [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111], // L
+    [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001], // M
+    [0b10001, 0b10001, 0b11001, 0b10101, 0b10011, 0b10001, 0b10001], // N
+    [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110], // O
+    [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000], // P
+    [0b01110, 0b10001, 0b10001, 0b10001, 0b10101, 0b10010, 0b01101], // Q
+    [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001], // R
+    [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110], // S
+    [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100], // T
+    [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110], // U
+    [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01010, 0b00100], // V
+    [0b10001, 0b10001, 0b10001, 0b10101, 0b10101, 0b10101, 0b01010], // W
+    [0b10001, 0b10001, 0b01010, 0b00100, 0b01010, 0b10001, 0b10001], // X
+    [0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100, 0b00100], // Y
+    [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b10000, 0b11111], // Z
+];
+
+#[rustfmt::skip]
+const DIGITS: [[u8; 7]; 10] = [
+    [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110], // 0
+    [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110], // 1
+    [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111], // 2
+    [0b11111, 0b00010, 0b00100, 0b00010, 0b00001, 0b10001, 0b01110], // 3
+    [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010], // 4
+    [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110], // 5
+    [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110], // 6
+    [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000], // 7
+    [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110], // 8
+    [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100], // 9
+];
+
+/// Bitmap rows for `c` (case-insensitive); None for characters outside the
+/// font, which render as blank space.
+pub fn glyph(c: char) -> Option<[u8; 7]> {
+    match c.to_ascii_uppercase() {
+        c @ 'A'..='Z' => Some(LETTERS[c as usize - 'A' as usize]),
+        c @ '0'..='9' => Some(DIGITS[c as usize - '0' as usize]),
+        _ => None,
+    }
+}
+
+/// Width in cells of `text` when rendered with this font.
+pub fn text_width(text: &str) -> i32 {
+    text.chars().count() as i32 * ADVANCE
+}
+
+/// Cell coordinates of every lit pixel of `text` with its top-left corner at
+/// (x, y). Coordinates may fall outside the frame; callers clip.
+pub fn text_cells(text: &str, x: i32, y: i32) -> impl Iterator<Item = (i32, i32)> + '_ {
+    text.chars().enumerate().flat_map(move |(n, c)| {
+        let rows = glyph(c).unwrap_or([0; 7]);
+        (0..GLYPH_HEIGHT).flat_map(move |row| {
+            (0..GLYPH_WIDTH)
+                .filter(move |col| rows[row as usize] & (1 << (GLYPH_WIDTH - 1 - col)) != 0)
+                .map(move |col| (x + n as i32 * ADVANCE + col, y + row))
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn letter_blits_its_bitmap() {
+        let mut grid = [[' '; 5]; 7];
+        for (x, y) in text_cells("t", 10, 3) {
+            grid[(y - 3) as usize][(x - 10) as usize] = '#';
+        }
+        let rows: Vec<String> = grid.iter().map(|r| r.iter().collect()).collect();
+        assert_eq!(rows, ["#####", "  #  ", "  #  ", "  #  ", "  #  ", "  #  ", "  #  "]);
+    }
+
+    #[test]
+    fn unknown_characters_are_blank_but_advance() {
+        assert_eq!(text_cells("?", 0, 0).count(), 0);
+        assert!(text_cells("?I", 0, 0).all(|(x, _)| x >= ADVANCE));
+        assert_eq!(text_width("?I"), 2 * ADVANCE);
+    }
+}
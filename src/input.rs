@@ -0,0 +1,233 @@
+use std::io;
+use std::time::Duration;
+
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent,
+    MouseEventKind,
+};
+
+use crate::math::{Mat3, Vec2, Vec3};
+
+/// What a key press or mouse gesture asks the animation to do.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Action {
+    Quit,
+    SpeedUp,
+    SpeedDown,
+    SpeedReset,
+    LightLeft,
+    LightRight,
+    LightUp,
+    LightDown,
+    // camera orbit deltas in radians: azimuth, elevation
+    Orbit(f32, f32),
+    // camera distance factor, < 1 moves closer
+    Dolly(f32),
+    // palette editor: gradient stop picked by the number keys, from 0
+    SelectStop(usize),
+    // save the current frame as an image
+    Snapshot,
+    // donut proportions: tube radius, then major radius
+    TubeShrink,
+    TubeGrow,
+    RingShrink,
+    RingGrow,
+    // step to the next render mode
+    CycleMode,
+}
+
+// camera orbit per dragged cell, and dolly factor per scroll notch
+pub const DRAG_STEP: f32 = 3.0 * std::f32::consts::PI / 180.0;
+pub const DOLLY_STEP: f32 = 0.9;
+
+/// Orbit angles for a drag of (dx, dy) cells: horizontal drags turn the
+/// azimuth, dragging up raises the camera.
+pub fn drag_to_orbit(dx: i32, dy: i32) -> (f32, f32) {
+    (dx as f32 * DRAG_STEP, -dy as f32 * DRAG_STEP)
+}
+
+// rotation speed multiplier step and bounds; negative spins backwards
+pub const SPEED_STEP: f32 = 0.25;
+pub const MAX_SPEED: f32 = 4.0;
+
+// how far one arrow press swings the light
+pub const LIGHT_STEP: f32 = 5.0 * std::f32::consts::PI / 180.0;
+
+/// New light direction after `action`, or None if it doesn't steer the light.
+/// Left/right swing it around the screen's vertical (Z) axis, up/down around
+/// the horizontal (Y) axis; the result is re-normalized so rounding errors
+/// don't accumulate.
+pub fn steer_light(light: Vec3, action: Action) -> Option<Vec3> {
+    let rotation = match action {
+        Action::LightLeft => Mat3::rotation_z(-LIGHT_STEP),
+        Action::LightRight => Mat3::rotation_z(LIGHT_STEP),
+        Action::LightUp => Mat3::rotation_y(-LIGHT_STEP),
+        Action::LightDown => Mat3::rotation_y(LIGHT_STEP),
+        _ => return None,
+    };
+    Some((rotation * light).norm())
+}
+
+/// New rotation speed after `action`, or None if it doesn't affect speed.
+pub fn apply_speed(speed: f32, action: Action) -> Option<f32> {
+    let speed = match action {
+        Action::SpeedUp => speed + SPEED_STEP,
+        Action::SpeedDown => speed - SPEED_STEP,
+        Action::SpeedReset => 1.0,
+        _ => return None,
+    };
+    Some(speed.clamp(-MAX_SPEED, MAX_SPEED))
+}
+
+// radius change per key press, and the smallest radius either can shrink to
+pub const RADIUS_STEP: f32 = 0.05;
+pub const MIN_RADIUS: f32 = 0.05;
+
+/// New (major, tube) radii after `action`, or None if it doesn't resize the
+/// donut. Neither goes below `MIN_RADIUS`.
+pub fn apply_radii(radii: Vec2, action: Action) -> Option<Vec2> {
+    let (dx, dy) = match action {
+        Action::TubeShrink => (0.0, -RADIUS_STEP),
+        Action::TubeGrow => (0.0, RADIUS_STEP),
+        Action::RingShrink => (-RADIUS_STEP, 0.0),
+        Action::RingGrow => (RADIUS_STEP, 0.0),
+        _ => return None,
+    };
+    Some(Vec2::new((radii.x + dx).max(MIN_RADIUS), (radii.y + dy).max(MIN_RADIUS)))
+}
+
+pub fn key_action(key: KeyEvent) -> Option<Action> {
+    if key.kind == KeyEventKind::Release {
+        return None;
+    }
+    match key.code {
+        KeyCode::Char('q') | KeyCode::Esc => Some(Action::Quit),
+        // '=' shares the key with '+' on most layouts
+        KeyCode::Char('+') | KeyCode::Char('=') => Some(Action::SpeedUp),
+        KeyCode::Char('-') => Some(Action::SpeedDown),
+        KeyCode::Char('0') => Some(Action::SpeedReset),
+        KeyCode::Char('s') => Some(Action::Snapshot),
+        KeyCode::Char('m') => Some(Action::CycleMode),
+        KeyCode::Char('[') => Some(Action::TubeShrink),
+        KeyCode::Char(']') => Some(Action::TubeGrow),
+        KeyCode::Char('{') => Some(Action::RingShrink),
+        KeyCode::Char('}') => Some(Action::RingGrow),
+        KeyCode::Char(c @ '1'..='6') => Some(Action::SelectStop(c as usize - '1' as usize)),
+        KeyCode::Left => Some(Action::LightLeft),
+        KeyCode::Right => Some(Action::LightRight),
+        KeyCode::Up => Some(Action::LightUp),
+        KeyCode::Down => Some(Action::LightDown),
+        // raw mode swallows SIGINT, so treat Ctrl-C as a key
+        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Some(Action::Quit),
+        _ => None,
+    }
+}
+
+/// Turns terminal events into actions, remembering where a drag started.
+#[derive(Debug, Default)]
+pub struct Input {
+    drag_from: Option<(u16, u16)>,
+}
+
+impl Input {
+    pub fn mouse_action(&mut self, mouse: MouseEvent) -> Option<Action> {
+        let at = (mouse.column, mouse.row);
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.drag_from = Some(at);
+                None
+            }
+            MouseEventKind::Drag(MouseButton::Left) => {
+                let from = self.drag_from.replace(at).unwrap_or(at);
+                let (az, el) =
+                    drag_to_orbit(at.0 as i32 - from.0 as i32, at.1 as i32 - from.1 as i32);
+                Some(Action::Orbit(az, el))
+            }
+            MouseEventKind::Up(MouseButton::Left) => {
+                self.drag_from = None;
+                None
+            }
+            MouseEventKind::ScrollUp => Some(Action::Dolly(DOLLY_STEP)),
+            MouseEventKind::ScrollDown => Some(Action::Dolly(1.0 / DOLLY_STEP)),
+            _ => None,
+        }
+    }
+
+    /// Drains pending terminal events without blocking and returns the
+    /// actions they map to, in order.
+    pub fn poll_actions(&mut self) -> io::Result<Vec<Action>> {
+        let mut actions = Vec::new();
+        while event::poll(Duration::ZERO)? {
+            match event::read()? {
+                Event::Key(key) => actions.extend(key_action(key)),
+                Event::Mouse(mouse) => actions.extend(self.mouse_action(mouse)),
+                _ => {}
+            }
+        }
+        Ok(actions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(c: char) -> Option<Action> {
+        key_action(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE))
+    }
+
+    #[test]
+    fn plus_minus_and_zero_set_the_speed() {
+        let speed_after = |keys: &str| {
+            keys.chars().filter_map(press).fold(1.0, |s, a| apply_speed(s, a).unwrap_or(s))
+        };
+        assert_eq!(speed_after("+"), 1.0 + SPEED_STEP);
+        assert_eq!(speed_after("="), 1.0 + SPEED_STEP);
+        assert_eq!(speed_after("-"), 1.0 - SPEED_STEP);
+        assert_eq!(speed_after("++++0"), 1.0);
+        assert_eq!(speed_after(&"+".repeat(40)), MAX_SPEED);
+        assert_eq!(speed_after(&"-".repeat(40)), -MAX_SPEED);
+        assert_eq!(apply_speed(2.0, Action::Snapshot), None);
+    }
+
+    #[test]
+    fn steered_light_stays_unit_length() {
+        use Action::{LightDown, LightLeft, LightRight, LightUp};
+        let steps = [LightLeft, LightUp, LightUp, LightRight, LightDown];
+        let mut light = Vec3::new(-1.0, 1.0, -1.0).norm();
+        for action in steps.into_iter().cycle().take(1000) {
+            light = steer_light(light, action).unwrap();
+            assert!((light.len() - 1.0).abs() < 1e-5);
+        }
+        assert_eq!(steer_light(light, Action::Quit), None);
+    }
+
+    #[test]
+    fn drags_turn_into_orbit_angles() {
+        assert_eq!(drag_to_orbit(0, 0), (0.0, 0.0));
+        assert_eq!(drag_to_orbit(10, 0), (10.0 * DRAG_STEP, 0.0));
+        // dragging up (toward row 0) raises the camera
+        assert_eq!(drag_to_orbit(0, -2), (0.0, 2.0 * DRAG_STEP));
+
+        let mouse = |kind, column, row| MouseEvent { kind, column, row, modifiers: KeyModifiers::NONE };
+        let mut input = Input::default();
+        assert_eq!(input.mouse_action(mouse(MouseEventKind::Down(MouseButton::Left), 5, 5)), None);
+        let drag = MouseEventKind::Drag(MouseButton::Left);
+        assert_eq!(input.mouse_action(mouse(drag, 8, 5)), Some(Action::Orbit(3.0 * DRAG_STEP, 0.0)));
+        // each step is measured from the last one, not from where it started
+        assert_eq!(input.mouse_action(mouse(drag, 8, 4)), Some(Action::Orbit(0.0, DRAG_STEP)));
+    }
+
+    #[test]
+    fn shrinking_radii_stops_at_the_lower_bound() {
+        let mut radii = Vec2::new(0.3, 0.12);
+        for _ in 0..10 {
+            radii = apply_radii(radii, Action::TubeShrink).unwrap();
+            radii = apply_radii(radii, Action::RingShrink).unwrap();
+        }
+        assert_eq!((radii.x, radii.y), (MIN_RADIUS, MIN_RADIUS));
+        let grown = apply_radii(radii, Action::TubeGrow).unwrap();
+        assert!((grown.y - (MIN_RADIUS + RADIUS_STEP)).abs() < 1e-6 && grown.x == MIN_RADIUS);
+        assert_eq!(apply_radii(radii, Action::Quit), None);
+    }
+}
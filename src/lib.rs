@@ -0,0 +1,25 @@
+pub mod budget;
+pub mod camera;
+pub mod color;
+pub mod completions;
+pub mod config;
+pub mod demo;
+pub mod error;
+pub mod floor;
+pub mod font;
+pub mod input;
+pub mod light;
+pub mod march;
+pub mod math;
+pub mod output;
+pub mod pacing;
+pub mod palette;
+pub mod pattern;
+pub mod post;
+pub mod render;
+pub mod rng;
+pub mod scene;
+pub mod screensaver;
+pub mod sdf;
+pub mod starfield;
+pub mod stats;
@@ -0,0 +1,264 @@
+use crossterm::style::Color;
+
+use crate::camera::Camera;
+use crate::math::{rotate_around_axis, Vec3};
+
+const WHITE: Color = Color::Rgb { r: 255, g: 255, b: 255 };
+
+/// Directional light; `dir` points from the surface toward the light.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Light {
+    pub dir: Vec3,
+    #[cfg_attr(feature = "serde", serde(with = "crate::color::hex"))]
+    pub color: Color,
+    pub intensity: f32,
+}
+
+impl Light {
+    pub fn white(dir: Vec3) -> Self {
+        Self { dir: dir.norm(), color: WHITE, intensity: 1.0 }
+    }
+}
+
+/// Half-Lambert falloff for `cos = n.dot(light)`: `wrap = 0` is plain
+/// Lambert, `wrap = 1` still lights the side facing away at zero.
+pub fn wrap_diffuse(cos: f32, wrap: f32) -> f32 {
+    (cos * (1.0 - wrap) + wrap).max(0.0)
+}
+
+/// Light direction `dir` carried `angle_rad` around the unit `axis`,
+/// re-normalized so rounding doesn't build up over a long orbit.
+pub fn orbit_light(dir: Vec3, axis: Vec3, angle_rad: f32) -> Vec3 {
+    rotate_around_axis(dir, axis, angle_rad).norm()
+}
+
+/// Summed diffuse light on a surface with normal `n`, and the mix of the
+/// light colors weighted by how much each one contributes.
+pub fn illuminate(lights: &[Light], n: Vec3, wrap: f32) -> (f32, Color) {
+    let (mut total, mut rgb) = (0.0, [0.0_f32; 3]);
+    for light in lights {
+        let w = light.intensity * wrap_diffuse(n.dot(light.dir), wrap);
+        let Color::Rgb { r, g, b } = light.color else { continue };
+        total += w;
+        for (sum, c) in rgb.iter_mut().zip([r, g, b]) {
+            *sum += w * c as f32;
+        }
+    }
+    if total <= 0.0 {
+        return (0.0, WHITE);
+    }
+    let [r, g, b] = rgb.map(|c| (c / total).round() as u8);
+    (total, Color::Rgb { r, g, b })
+}
+
+/// How specular highlights are computed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum SpecularModel {
+    // the light mirrored about the normal, dotted with the view direction
+    Phong,
+    // the normal dotted with the half-vector between light and view
+    Blinn,
+}
+
+impl SpecularModel {
+    pub const ALL: [SpecularModel; 2] = [SpecularModel::Phong, SpecularModel::Blinn];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SpecularModel::Phong => "phong",
+            SpecularModel::Blinn => "blinn",
+        }
+    }
+}
+
+impl std::str::FromStr for SpecularModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SpecularModel::ALL
+            .into_iter()
+            .find(|m| m.name() == s)
+            .ok_or_else(|| format!("unknown specular model: {s} (expected phong or blinn)"))
+    }
+}
+
+/// Highlight strength in `[0, 1]` for normal `n`, light direction `l` and
+/// `view` (from the surface toward the eye), all unit length.
+///
+/// Phong reflects the light, `r = 2 (n·l) n - l`, and takes `(r·view)^k`.
+/// Blinn-Phong takes `(n·h)^k` with the half-vector `h = normalize(l +
+/// view)`, halfway between light and eye; it is cheaper and doesn't cut
+/// the highlight off at grazing angles, where `r·view` goes negative. For
+/// the same look Blinn needs about four times the exponent. Zero from
+/// lights behind the surface.
+pub fn specular(model: SpecularModel, n: Vec3, l: Vec3, view: Vec3, shininess: f32) -> f32 {
+    let cos = n.dot(l);
+    if cos <= 0.0 {
+        return 0.0;
+    }
+    let base = match model {
+        SpecularModel::Phong => n.mul(2.0 * cos).sub(l).dot(view),
+        SpecularModel::Blinn => n.dot(l.add(view).norm()),
+    };
+    base.max(0.0).powf(shininess)
+}
+
+/// Summed highlights from every light on a surface seen along `view`.
+pub fn highlights(lights: &[Light], model: SpecularModel, n: Vec3, view: Vec3, shininess: f32) -> f32 {
+    lights.iter().map(|l| l.intensity * specular(model, n, l.dir, view, shininess)).sum()
+}
+
+/// Multiplies `c` by the light color `tint`; white leaves it unchanged.
+pub fn apply_tint(c: Color, tint: Color) -> Color {
+    match (c, tint) {
+        (Color::Rgb { r, g, b }, Color::Rgb { r: tr, g: tg, b: tb }) => {
+            let mul = |c: u8, t: u8| (c as u16 * t as u16 / 255) as u8;
+            Color::Rgb { r: mul(r, tr), g: mul(g, tg), b: mul(b, tb) }
+        }
+        (other, _) => other,
+    }
+}
+
+/// Ready-made light rigs, placed relative to the camera.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Lighting {
+    // key, fill and rim: 3 lights
+    ThreePoint,
+    // strong high side key and a faint fill: 2 lights
+    Rembrandt,
+    // a single light from behind
+    Silhouette,
+    // a single light from straight above
+    TopDown,
+}
+
+impl Lighting {
+    pub const ALL: [Lighting; 4] =
+        [Lighting::ThreePoint, Lighting::Rembrandt, Lighting::Silhouette, Lighting::TopDown];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Lighting::ThreePoint => "three-point",
+            Lighting::Rembrandt => "rembrandt",
+            Lighting::Silhouette => "silhouette",
+            Lighting::TopDown => "top-down",
+        }
+    }
+
+    pub fn lights(self, camera: &Camera) -> Vec<Light> {
+        let basis = camera.basis();
+        let (forward, right, down) = (basis.col(0), basis.col(1), basis.col(2));
+        // from the surface: toward the camera, to its right, and up
+        let toward = |front: f32, side: f32, up: f32| {
+            forward.mul(-front).add(right.mul(side)).add(down.mul(-up)).norm()
+        };
+        let light = |dir: Vec3, (r, g, b): (u8, u8, u8), intensity: f32| Light {
+            dir,
+            color: Color::Rgb { r, g, b },
+            intensity,
+        };
+        match self {
+            Lighting::ThreePoint => vec![
+                light(toward(1.0, -1.0, 1.0), (255, 244, 229), 1.0),
+                light(toward(1.0, 1.0, 0.0), (200, 220, 255), 0.4),
+                light(toward(-1.0, 0.5, 1.0), (255, 255, 255), 0.7),
+            ],
+            Lighting::Rembrandt => vec![
+                light(toward(0.5, -1.0, 1.0), (255, 230, 200), 1.1),
+                light(toward(1.0, 1.0, 0.0), (210, 220, 255), 0.15),
+            ],
+            Lighting::Silhouette => vec![light(toward(-1.0, 0.0, 0.3), (255, 255, 255), 1.2)],
+            Lighting::TopDown => vec![light(toward(0.0, 0.0, 1.0), (255, 255, 255), 1.0)],
+        }
+    }
+}
+
+impl std::str::FromStr for Lighting {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Lighting::ALL
+            .into_iter()
+            .find(|l| l.name() == s)
+            .ok_or_else(|| format!("unknown lighting preset: {s}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::{add_light, Tonemap};
+
+    #[test]
+    fn presets_have_their_documented_lights() {
+        let mut camera = Camera::default();
+        camera.orbit(0.7, 0.3);
+        for (preset, count) in Lighting::ALL.into_iter().zip([3, 2, 1, 1]) {
+            let lights = preset.lights(&camera);
+            assert_eq!(lights.len(), count, "{preset:?}");
+            assert!(lights.iter().all(|l| (l.dir.len() - 1.0).abs() < 1e-5), "{preset:?}");
+        }
+    }
+
+    #[test]
+    fn wrap_lights_the_back_and_zero_wrap_is_lambert() {
+        let l = Vec3::new(0.3, -0.4, 0.5).norm();
+        for n in [l, Vec3::new(1.0, 0.0, 0.0), l.mul(-1.0), Vec3::new(0.0, 0.6, 0.8)] {
+            assert_eq!(wrap_diffuse(n.dot(l), 0.0), n.dot(l).max(0.0));
+        }
+        // facing away: dark without wrap, still lit with it
+        assert_eq!(wrap_diffuse(-0.5, 0.0), 0.0);
+        assert!(wrap_diffuse(-0.5, 1.0) > 0.0);
+        assert!(wrap_diffuse(-0.5, 0.5) > 0.0);
+        assert!(wrap_diffuse(-1.0, 1.0) > 0.0);
+    }
+
+    #[test]
+    fn phong_and_blinn_agree_head_on() {
+        let n = Vec3::new(0.0, 0.0, 1.0);
+        // light and eye straight above: the peak of both highlights
+        for model in SpecularModel::ALL {
+            assert!((specular(model, n, n, n, 16.0) - 1.0).abs() < 1e-6);
+        }
+        // a little off: Blinn at four times the exponent looks the same
+        let l = Vec3::new(0.1f32.sin(), 0.0, 0.1f32.cos());
+        let phong = specular(SpecularModel::Phong, n, l, n, 16.0);
+        let blinn = specular(SpecularModel::Blinn, n, l, n, 64.0);
+        assert!((phong - blinn).abs() < 0.02, "{phong} vs {blinn}");
+        assert!(phong < 1.0);
+    }
+
+    #[test]
+    fn full_light_orbit_returns_to_the_start() {
+        let (dir, axis) = (Vec3::new(0.3, -0.5, 0.8).norm(), Vec3::new(0.0, 1.0, 1.0).norm());
+        // one turn at once, and the same turn a degree per frame
+        let once = orbit_light(dir, axis, std::f32::consts::TAU);
+        let stepped = (0..360).fold(dir, |d, _| orbit_light(d, axis, 1f32.to_radians()));
+        for end in [once, stepped] {
+            assert!(end.sub(dir).len() < 1e-4, "{end:?}");
+            assert!((end.len() - 1.0).abs() < 1e-6);
+        }
+        // halfway round it is elsewhere, at the same angle to the axis
+        let half = orbit_light(dir, axis, std::f32::consts::PI);
+        assert!(half.sub(dir).len() > 0.5 && (half.dot(axis) - dir.dot(axis)).abs() < 1e-5);
+    }
+
+    #[test]
+    fn mirror_aligned_highlight_on_black_shows_the_specular_color() {
+        let tint = Color::Rgb { r: 140, g: 200, b: 255 };
+        let black = Color::Rgb { r: 0, g: 0, b: 0 };
+        // light and eye mirrored about the normal: a full-strength highlight
+        let n = Vec3::new(0.0, 0.0, 1.0);
+        let (l, view) = (Vec3::new(0.6, 0.0, 0.8), Vec3::new(-0.6, 0.0, 0.8));
+        for model in SpecularModel::ALL {
+            let spec = highlights(&[Light::white(l)], model, n, view, 32.0);
+            assert!((spec - 1.0).abs() < 1e-5, "{model:?}: {spec}");
+            assert_eq!(add_light(black, tint, spec, Tonemap::None), tint);
+        }
+    }
+}
@@ -1,247 +1,414 @@
-use std::f32::consts::PI;
-use std::io::{stdout, Write};
+use std::fs::{File, OpenOptions};
+use std::io::{self, stdout, BufWriter, IsTerminal, LineWriter, Stdout, Write};
+use std::path::Path;
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use crossterm::{
-    cursor::{Hide, MoveTo, Show},
+    cursor::MoveTo,
+    event::{DisableMouseCapture, EnableMouseCapture},
     execute, queue,
-    style::{Color, SetForegroundColor, ResetColor},
-    terminal::{size as term_size, EnterAlternateScreen, LeaveAlternateScreen},
+    style::Color,
+    terminal::{disable_raw_mode, enable_raw_mode, size as term_size, window_size},
 };
 
-const COLOR_SCALE: f32 = 1.5; // Adjusted scaling factor for color intensity
-
-#[derive(Copy, Clone, Debug, Default)]
-struct Vec2 {
-    x: f32,
-    y: f32,
-}
-impl Vec2 {
-    fn new(x: f32, y: f32) -> Self { Self { x, y } }
-}
+use torus::budget::{Budget, Refine};
+use torus::color::{to_ansi256, ColorDepth};
+use torus::completions::script;
+use torus::config::Config;
+use torus::demo::{dim, fade, segment_at, segment_renderer, total_frames, SEGMENTS};
+use torus::error::DonutError;
+use torus::input::{apply_radii, apply_speed, steer_light, Action, Input};
+use torus::output::{
+    cell_aspect, letterbox, locked_viewport, mark_background, query_window_size, rgb_bytes, save_snapshot,
+    scale_nearest, write_frame, write_ppm, write_sixel, write_text, write_theme_list, Cells, Screen,
+    QUERY_TIMEOUT,
+};
+use torus::pacing::Pacer;
+use torus::palette::PaletteEditor;
+use torus::post::dither_ansi256;
+use torus::render::{render_frame_into, Frame, Renderer};
+use torus::rng::time_seed;
+use torus::stats::{checksum, BenchSummary, FrameStats, Histogram, Profile, StageTimes};
 
-#[derive(Copy, Clone, Debug, Default)]
-struct Vec3 {
-    x: f32,
-    y: f32,
-    z: f32,
-}
+// how long a notice (snapshot saved, render mode) stays up, in frames
+const NOTICE_FRAMES: u32 = 60;
 
-impl Vec3 {
-    fn new(x: f32, y: f32, z: f32) -> Self { Self { x, y, z } }
-    fn add(self, o: Vec3) -> Self { Self::new(self.x + o.x, self.y + o.y, self.z + o.z) }
-    fn sub(self, o: Vec3) -> Self { Self::new(self.x - o.x, self.y - o.y, self.z - o.z) }
-    fn mul(self, s: f32) -> Self { Self::new(self.x * s, self.y * s, self.z * s) }
-    fn dot(self, o: Vec3) -> f32 { self.x * o.x + self.y * o.y + self.z * o.z }
-    fn len(self) -> f32 { self.dot(self).sqrt() }
-    fn norm(self) -> Self {
-        let l = self.len();
-        if l > 0.0 { self.mul(1.0 / l) } else { self }
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut cfg = Config::from_args(std::env::args().skip(1))?;
+    if let Some(shell) = cfg.completions {
+        print!("{}", script(shell, env!("CARGO_BIN_NAME")));
+        return Ok(());
+    }
+    if cfg.list_themes {
+        write_theme_list(&mut stdout(), !cfg.ascii_only)?;
+        return Ok(());
+    }
+    if cfg.seed.is_none() {
+        // print it so a run worth keeping can be replayed with --seed
+        let seed = time_seed();
+        eprintln!("seed: {seed}");
+        cfg.seed = Some(seed);
+    }
+    if cfg.dump_config {
+        // after picking the seed, so the dump reproduces this run
+        return dump_config(&cfg);
     }
-}
-
-fn rot_z(v: Vec3, angle_rad: f32) -> Vec3 {
-    let (s, c) = angle_rad.sin_cos();
-    // rotate the Y–Z plane like original AZ quaternion-from-euler(z)
-    // (their camera used X forward; we keep X as-is, rotate (y,z))
-    Vec3::new(
-        v.x,
-        c * v.y - s * v.z,
-        s * v.y + c * v.z,
-    )
-}
 
-fn sd_torus(p: Vec3, t: Vec2, tdir: Vec3) -> f32 {
-    // project p onto plane orthogonal to tdir,
-    // then pull it onto the major radius circle (length = t.x),
-    // distance to that circle minus tube radius t.y
-    let p_proj = p.sub(tdir.mul(p.dot(tdir)));
-    let p_proj = p_proj.norm().mul(t.x);
-    p_proj.sub(p).len() - t.y
-}
+    if let Some(t) = cfg.frame.filter(|_| cfg.headless || cfg.ppm.is_some()) {
+        return render_still(&cfg, t);
+    }
+    if let Some(seconds) = cfg.bench {
+        return bench(&cfg, Duration::from_secs_f32(seconds));
+    }
+    if cfg.checksum {
+        return print_checksums(&cfg);
+    }
+    if cfg.pipe_rgb {
+        return pipe_rgb(&cfg);
+    }
 
-fn torus_normal(p: Vec3, t: Vec2, tdir: Vec3) -> Vec3 {
-    let eps = 0.005;
-    let dx = sd_torus(Vec3::new(p.x + eps, p.y, p.z), t, tdir)
-        - sd_torus(Vec3::new(p.x - eps, p.y, p.z), t, tdir);
-    let dy = sd_torus(Vec3::new(p.x, p.y + eps, p.z), t, tdir)
-        - sd_torus(Vec3::new(p.x, p.y - eps, p.z), t, tdir);
-    let dz = sd_torus(Vec3::new(p.x, p.y, p.z + eps), t, tdir)
-        - sd_torus(Vec3::new(p.x, p.y, p.z - eps), t, tdir);
-    Vec3::new(dx, dy, dz).norm()
-}
+    // terminal setup; raw mode lets single key presses through for quitting
+    let mut out = stdout();
+    let screen = cfg.screen();
+    screen.setup(&mut out).map_err(DonutError::Terminal)?;
+    enable_raw_mode().map_err(DonutError::Terminal)?;
+    // drag to orbit the camera, scroll to dolly
+    execute!(out, EnableMouseCapture).map_err(DonutError::Terminal)?;
 
-// Helper to linearly interpolate between two u8 values
-fn lerp(a: u8, b: u8, t: f32) -> u8 {
-    (a as f32 + (b as f32 - a as f32) * t).round().clamp(0.0, 255.0) as u8
-}
+    let mut times = Vec::new();
+    let mut editor = cfg
+        .edit_palette
+        .then(|| PaletteEditor::new(cfg.palette.as_deref().unwrap_or(cfg.theme.gradient())));
+    let mut profile = cfg.profile.then(Profile::default);
+    let mut last = None;
+    let result = run(&cfg, &mut out, &mut times, &mut editor, &mut profile, &mut last);
 
-// Helper to linearly interpolate between two Color::Rgb
-fn lerp_color(a: &Color, b: &Color, t: f32) -> Color {
-    match (a, b) {
-        (Color::Rgb { r: r1, g: g1, b: b1 }, Color::Rgb { r: r2, g: g2, b: b2 }) => {
-            Color::Rgb {
-                r: lerp(*r1, *r2, t),
-                g: lerp(*g1, *g2, t),
-                b: lerp(*b1, *b2, t),
-            }
-        }
-        _ => *a, // fallback, should not happen
+    // restore terminal, even when rendering failed
+    execute!(out, DisableMouseCapture).map_err(DonutError::Terminal)?;
+    disable_raw_mode().map_err(DonutError::Terminal)?;
+    let background = cfg.background.unwrap_or(' ');
+    screen
+        .teardown_persisting(&mut out, last.as_ref(), !cfg.ascii_only, background)
+        .map_err(DonutError::Terminal)?;
+    if !times.is_empty() {
+        eprintln!("render time per frame ({} frames):", times.len());
+        eprintln!("{}", Histogram::new(&times, 10).to_text());
     }
+    if let Some(profile) = profile.filter(|p| p.frames > 0) {
+        eprintln!("time per frame by stage ({} frames):", profile.frames);
+        eprintln!("{}", profile.to_text());
+    }
+    if let Some(editor) = editor {
+        println!("{}", editor.to_text());
+    }
+    result
 }
 
-fn get_color_from_intensity(intensity: f32) -> Color {
-    // Gradient stops: (threshold, Color)
-    // Blue to orange temperature gradient
-    const GRADIENT: &[(f32, Color)] = &[
-        (0.0,  Color::Rgb { r: 0,   g: 0,   b: 150 }),   // Deep blue
-        (0.2,  Color::Rgb { r: 50,  g: 100, b: 255 }),   // Medium blue
-        (0.4,  Color::Rgb { r: 100, g: 200, b: 255 }),   // Cyan
-        (0.6,  Color::Rgb { r: 200, g: 255, b: 155 }),   // Yellowish
-        (0.8,  Color::Rgb { r: 255, g: 155, b: 0   }),   // Orange
-        (1.0,  Color::Rgb { r: 255, g: 255, b: 100 }),   // White-orange
-    ];
-    
-    let clamped = intensity.max(0.0).min(1.0);
-    
-    // Find the two stops between which clamped falls
-    for i in 0..GRADIENT.len() - 1 {
-        let (t0, c0) = GRADIENT[i];
-        let (t1, c1) = GRADIENT[i + 1];
-        if clamped >= t0 && clamped <= t1 {
-            let t = (clamped - t0) / (t1 - t0);
-            return lerp_color(&c0, &c1, t);
-        }
-    }
-    
-    // Fallback: if clamped == 1.0 exactly, return last color
-    GRADIENT.last().unwrap().1
-}
-
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // terminal setup
-    let mut out = stdout();
-    execute!(out, EnterAlternateScreen, Hide)?;
+/// Runs the animation, collecting each frame's render time into `times`
+/// and, given a `profile`, the time of each stage into that. With an
+/// `editor` the arrow keys edit its palette instead of the light. With
+/// `--persist` the last frame drawn is kept in `last`.
+fn run(
+    cfg: &Config,
+    out: &mut Stdout,
+    times: &mut Vec<Duration>,
+    editor: &mut Option<PaletteEditor>,
+    profile: &mut Option<Profile>,
+    last: &mut Option<Cells>,
+) -> Result<(), Box<dyn std::error::Error>> {
     let (mut width, mut height) = term_size()?;
     if width < 20 { width = 80; }
     if height < 10 { height = 24; }
+    let screen = cfg.screen();
+    if screen == Screen::Inline {
+        // keep a line for the prompt so the first row doesn't scroll away
+        height -= 1;
+    }
 
-    // aspect and shading
-    let aspect = width as f32 / height as f32;
-    let pixel_aspect = 11.0f32 / 24.0; // non-square terminal pixels
-    let gradient = b" .:-=+*#%@"; // Brighter character progression
-    let grad_size = (gradient.len() as i32) - 1;
-    let min_col = 1.0 / grad_size as f32;
-
-    // scene parameters
-    let moving = 20_000;                         // frames
-    let light = Vec3::new(-1.0, -1.0, -1.0).norm();
-    let ro = Vec3::new(-2.5, 0.0, 0.0);          // camera origin
-    let in_rad = 0.3_f32;                        // tube radius
-    let out_rad = 1.2_f32;                       // main radius
-    let camp_pos_x = -2.0_f32;                   // like C++ variable name
-    let torus = Vec2::new(out_rad, in_rad);
-
-    let mut frame_buf = vec![b' '; (width as usize) * (height as usize)];
-    let mut color_buf = vec![Color::Reset; (width as usize) * (height as usize)];
-
-    for t in 0..moving {
-        // rotate torus axis over time: start with (1,1,1) and rotate around Z
-        let base_axis = Vec3::new(1.0, 1.0, 1.0).norm();
-        // original used "degrees = t", convert to radians; slow it down a bit
-        let angle = (t as f32) * 0.6_f32 * (PI / 180.0);
-        let tdir = rot_z(base_axis, angle).norm();
-
-        // clear frame buffer
-        frame_buf.fill(b' ');
-        color_buf.fill(Color::Reset);
-
-        for j in 0..height {
-            for i in 0..width {
-                // uv in [-1, 1], correct aspect and pixel aspect
-                let mut ux = (i as f32 / width as f32) * 2.0 - 1.0;
-                let uy = (j as f32 / height as f32) * 2.0 - 1.0;
-                ux *= aspect * pixel_aspect;
-
-                // ray dir: X forward (like original: rd = normalize(1, uv.x, uv.y))
-                let rd = Vec3::new(1.0, ux, uy).norm();
-
-                // simple marching along rd up to a rough far bound
-                let mut diff = 0.0_f32;
-                let far = out_rad * 2.0 - camp_pos_x;
-                let mut k = 0.0_f32;
-                while k < far {
-                    let p = ro.add(rd.mul(k));
-                    let d = sd_torus(p, torus, tdir);
-                    if d < in_rad {
-                        let n = torus_normal(p, torus, tdir);
-                        diff += n.dot(light).max(min_col);
-                        break;
-                    }
-                    // step similar to tube radius; the C++ used fixed inRad steps
-                    k += in_rad;
-                }
+    let mut renderer = Renderer::new(cfg);
+    // keeps the 11/24 default where the terminal doesn't tell; Sixel
+    // pixels are square
+    if cfg.sixel.is_some() {
+        renderer.pixel_aspect = 1.0;
+    } else if let Some(aspect) = match window_size().ok().and_then(cell_aspect) {
+        // many terminals leave the pixels at zero; ask them directly
+        None => query_window_size(out, QUERY_TIMEOUT)?.and_then(cell_aspect),
+        aspect => aspect,
+    } {
+        renderer.pixel_aspect = aspect;
+    }
 
-                let mut ci = (diff * 20.0) as i32;
-                if ci < 0 { ci = 0; }
-                if ci > grad_size { ci = grad_size; }
-                let px = gradient[ci as usize];
-                
-                // Calculate color based on lighting intensity with better blending
-                let raw_intensity = diff / COLOR_SCALE; // More sensitive to lighting changes
-                let intensity = raw_intensity.clamp(0.1, 1.0); // Ensure minimum brightness
-                let color = get_color_from_intensity(intensity);
-
-                let idx = (i as usize) + (j as usize) * (width as usize);
-                frame_buf[idx] = px;
-                color_buf[idx] = color;
+    // a huge terminal renders at the cap and letterboxes the rest
+    let render = (
+        cfg.max_width.map_or(width, |w| w.clamp(1, width)),
+        cfg.max_height.map_or(height, |h| h.clamp(1, height)),
+    );
+    let render = if cfg.lock_aspect { locked_viewport(render, renderer.pixel_aspect) } else { render };
+    let mut input = Input::default();
+    // a canvas is rendered at its own size and scaled onto the render area
+    let canvas = cfg.canvas.unwrap_or(render);
+    let mut frame = Frame::new(canvas.0, canvas.1);
+    let mut stats = open_stats(cfg)?;
+    let mut budget = cfg.budget_ms.map(|ms| Budget::new(Duration::from_secs_f32(ms / 1000.0)));
+    let mut refine = cfg.progressive.then(Refine::default);
+    // the budget's subsample factor, before refinement coarsens it further
+    let mut subsample = cfg.subsample;
+    // what the last snapshot was saved as, and for how many more frames to say so
+    let mut notice: Option<(String, u32)> = None;
+
+    let frames = match cfg.frame {
+        Some(t) => t..t + 1,
+        None if cfg.demo => 0..total_frames(),
+        None => cfg.frame_range(),
+    };
+    let frames_start = frames.start;
+    // about 60 fps
+    let mut pacer = Pacer::new(Duration::from_millis(16), Instant::now());
+    // ascii-only frames headed for a file go out as plain text rows; a live
+    // terminal still places them, as a bare \n doesn't return in raw mode
+    let text = (cfg.ascii_only && !out.is_terminal()).then_some(cfg.line_ending);
+    for t in frames {
+        // each demo segment starts over with its own renderer
+        if cfg.demo
+            && let Some((k, 0)) = segment_at(t)
+        {
+            renderer = segment_renderer(cfg, k, &renderer);
+        }
+        let actions = input.poll_actions()?;
+        let mut snapshot = false;
+        if let Some(refine) = &mut refine {
+            if !actions.is_empty() {
+                refine.input();
             }
+            renderer.subsample = refine.next_subsample().max(subsample);
         }
-
-        // draw with optimized color batching
-        queue!(out, MoveTo(0, 0))?; // Move to top-left
-        
-        for j in 0..height {
-            queue!(out, MoveTo(0, j))?; // Move to start of each line
-            
-            let mut current_color = Color::Reset;
-            let mut line_buffer = String::new();
-            
-            for i in 0..width {
-                let idx = (i as usize) + (j as usize) * (width as usize);
-                let ch = frame_buf[idx] as char;
-                let color = color_buf[idx];
-                
-                // Only change color when it's different from current
-                if color != current_color {
-                    // Flush any buffered characters with previous color
-                    if !line_buffer.is_empty() {
-                        queue!(out, SetForegroundColor(current_color))?;
-                        write!(out, "{}", line_buffer)?;
-                        line_buffer.clear();
-                    }
-                    current_color = color;
+        for action in actions {
+            if let Some(editor) = editor
+                && editor.apply(action)
+            {
+                renderer.palette = Some(editor.stops.clone());
+                continue;
+            }
+            match action {
+                Action::Quit => return Ok(()),
+                Action::Orbit(az, el) => renderer.camera.orbit(az, el),
+                Action::Dolly(factor) => renderer.camera.dolly(factor),
+                Action::Snapshot => snapshot = true,
+                Action::CycleMode => {
+                    renderer.mode = renderer.mode.next();
+                    notice = Some((format!("mode: {}", renderer.mode.name()), NOTICE_FRAMES));
                 }
-                
-                line_buffer.push(ch);
+                _ => {}
+            }
+            if let Some(speed) = apply_speed(renderer.speed(), action) {
+                renderer.set_speed(t, speed);
             }
-            
-            // Flush remaining characters for this line
-            if !line_buffer.is_empty() {
-                queue!(out, SetForegroundColor(current_color))?;
-                write!(out, "{}", line_buffer)?;
+            if let Some(radii) = renderer.radii().and_then(|r| apply_radii(r, action)) {
+                renderer.set_radii(radii);
+            }
+            if let Some(key) = renderer.lights.first_mut()
+                && let Some(dir) = steer_light(key.dir, action)
+            {
+                key.dir = dir;
+            }
+        }
+
+        let started = Instant::now();
+        render_frame_into(&mut renderer, t, &mut frame);
+        let elapsed = started.elapsed();
+        // warmup frames are drawn but left out of the numbers
+        let counted = cfg.counts_frame(t, frames_start);
+        if counted {
+            times.push(elapsed);
+        }
+        // saved before any overlay is drawn on top
+        if snapshot {
+            notice = Some((format!("saved {}", save_snapshot(&frame, Path::new("."))?), NOTICE_FRAMES));
+        }
+        if cfg.demo
+            && let Some((k, local)) = segment_at(t)
+        {
+            let segment = &SEGMENTS[k];
+            dim(&mut frame.colors, fade(local, segment.frames));
+            frame.put_text(1, 0, segment.caption, Color::Rgb { r: 255, g: 255, b: 255 });
+        }
+        if let Some(editor) = editor {
+            editor.draw(&mut frame);
+        }
+        if let Some((text, frames)) = &mut notice {
+            frame.put_text(0, frame.height - 1, text, Color::Rgb { r: 255, g: 255, b: 255 });
+            *frames -= 1;
+            if *frames == 0 {
+                notice = None;
+            }
+        }
+        if let Some(budget) = &mut budget {
+            subsample = budget.next_subsample(elapsed, subsample);
+            renderer.subsample = subsample;
+        }
+        if let Some(file) = stats.as_mut().filter(|_| counted) {
+            writeln!(file, "{}", FrameStats::new(t, &frame, elapsed).to_json())?;
+        }
+
+        // draw, emitting color escapes only on changes
+        let writing = Instant::now();
+        if t > frames_start && text.is_none() {
+            screen.rewind(out, height)?;
+        }
+        if cfg.background.is_some() {
+            mark_background(&mut frame, cfg.background_color);
+        }
+        let background = cfg.background.unwrap_or(' ');
+        let quantized = if cfg.dither {
+            Some(dither_ansi256(&frame.colors, canvas.0 as usize, canvas.1 as usize))
+        } else if cfg.ansi256 {
+            Some(frame.colors.iter().map(|&c| to_ansi256(c)).collect())
+        } else if cfg.preview_palette != ColorDepth::Truecolor {
+            Some(frame.colors.iter().map(|&c| cfg.preview_palette.apply(c)).collect())
+        } else {
+            None
+        };
+        let colors = quantized.as_deref().unwrap_or(&frame.colors);
+        let scaled = (canvas != render).then(|| scale_nearest(&frame.chars, colors, canvas, render));
+        let (chars, colors) = match &scaled {
+            Some((chars, colors)) => (&chars[..], &colors[..]),
+            None => (&frame.chars[..], colors),
+        };
+        if let Some(block) = cfg.sixel {
+            queue!(out, MoveTo(0, 0))?;
+            write_sixel(out, colors, render, block)?;
+        } else {
+            let boxed = (render != (width, height)).then(|| letterbox(chars, colors, render, (width, height)));
+            let (chars, colors) = match &boxed {
+                Some((chars, colors)) => (&chars[..], &colors[..]),
+                None => (chars, colors),
+            };
+            write_frame(out, chars, colors, width, height, screen, !cfg.ascii_only, background, text)?;
+            if cfg.persist {
+                *last = Some((chars.to_vec(), colors.to_vec(), (width, height)));
             }
         }
-        
-        queue!(out, ResetColor)?;
         out.flush()?;
+        if let Some(profile) = profile.as_mut().filter(|_| counted) {
+            profile.add(StageTimes { write: writing.elapsed(), ..renderer.stage_times });
+        }
+
+        sleep(pacer.wait(Instant::now()));
+    }
 
-        // small delay so it’s visible; adjust or remove as you like
+    // a single still stays up until the user quits
+    while cfg.frame.is_some() && !input.poll_actions()?.contains(&Action::Quit) {
         sleep(Duration::from_millis(16));
     }
 
-    // restore terminal
-    execute!(out, Show, LeaveAlternateScreen)?;
     Ok(())
 }
+
+/// Renders frame `t` without touching the terminal.
+fn render_still(cfg: &Config, t: u32) -> Result<(), Box<dyn std::error::Error>> {
+    let (width, height) = cfg.size.unwrap_or((80, 24));
+    let mut frame = Frame::new(width, height);
+    let started = Instant::now();
+    render_frame_into(&mut Renderer::new(cfg), t, &mut frame);
+    if let Some(mut file) = open_stats(cfg)? {
+        writeln!(file, "{}", FrameStats::new(t, &frame, started.elapsed()).to_json())?;
+    }
+
+    if cfg.headless {
+        let mut out = stdout().lock();
+        write_text(&mut out, &frame, cfg.line_ending)?;
+        out.flush()?;
+    }
+    if let Some(path) = &cfg.ppm {
+        let mut file = BufWriter::new(File::create(path)?);
+        write_ppm(&mut file, &frame)?;
+        file.flush()?;
+    }
+    Ok(())
+}
+
+/// Renders frames back to back without drawing them for `duration`, then
+/// reports the frame rate.
+fn bench(cfg: &Config, duration: Duration) -> Result<(), Box<dyn std::error::Error>> {
+    let (width, height) = cfg.size.unwrap_or((80, 24));
+    let mut renderer = Renderer::new(cfg);
+    let mut frame = Frame::new(width, height);
+    let mut times = Vec::new();
+    let mut frames = cfg.frame_range();
+    for t in frames.by_ref().take(cfg.warmup as usize) {
+        render_frame_into(&mut renderer, t, &mut frame);
+    }
+    let start = Instant::now();
+    for t in frames {
+        if start.elapsed() >= duration {
+            break;
+        }
+        let started = Instant::now();
+        render_frame_into(&mut renderer, t, &mut frame);
+        times.push(started.elapsed());
+    }
+    eprintln!("{width}x{height}: {}", BenchSummary::new(&times, start.elapsed()).to_text());
+    Ok(())
+}
+
+/// Prints a stable hash of each frame, one `frame hash` line per frame.
+fn print_checksums(cfg: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let (width, height) = cfg.size.unwrap_or((80, 24));
+    let mut renderer = Renderer::new(cfg);
+    let mut frame = Frame::new(width, height);
+    let mut out = stdout().lock();
+    let frames = match cfg.frame {
+        Some(t) => t..t + 1,
+        None => cfg.frame_range(),
+    };
+    for t in frames {
+        render_frame_into(&mut renderer, t, &mut frame);
+        writeln!(out, "{t} {:016x}", checksum(&frame))?;
+    }
+    Ok(())
+}
+
+/// Streams every frame to stdout as raw RGB24 video, one pixel per cell.
+fn pipe_rgb(cfg: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let (width, height) = cfg.size.unwrap_or((80, 24));
+    eprintln!("ffmpeg -f rawvideo -pix_fmt rgb24 -s {width}x{height} -r 60 -i - out.mp4");
+
+    let mut renderer = Renderer::new(cfg);
+    let mut frame = Frame::new(width, height);
+    let mut out = stdout().lock();
+    let frames = match cfg.frame {
+        Some(t) => t..t + 1,
+        None => cfg.frame_range(),
+    };
+    for t in frames {
+        render_frame_into(&mut renderer, t, &mut frame);
+        match out.write_all(&rgb_bytes(&frame)) {
+            // the encoder hung up, e.g. ffmpeg reached its -frames limit
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+            result => result?,
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+fn dump_config(cfg: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    print!("{}", cfg.to_toml()?);
+    Ok(())
+}
+
+#[cfg(not(feature = "serde"))]
+fn dump_config(_: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    Err("--dump-config needs the serde feature".into())
+}
+
+/// Opens the `--stats` file for appending, if one was given. Lines are
+/// flushed as they're written so a killed run keeps its stats.
+fn open_stats(cfg: &Config) -> io::Result<Option<LineWriter<File>>> {
+    cfg.stats
+        .as_ref()
+        .map(|path| OpenOptions::new().create(true).append(true).open(path).map(LineWriter::new))
+        .transpose()
+}
@@ -0,0 +1,231 @@
+use crate::math::Vec3;
+
+// hit threshold right at the ray origin, where a scaled one would be zero
+const MIN_EPSILON: f32 = 1e-4;
+
+/// How rays step through the scene.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RaymarchConfig {
+    // give up on a ray after this many distance evaluations
+    pub max_steps: u32,
+    // None keeps the original fixed steps of one hit tolerance. Some(m)
+    // sphere traces instead, stepping by the distance bound but at least m:
+    // a larger floor stops grazing rays from crawling along a surface, at
+    // the cost of stepping over features thinner than m
+    pub min_step: Option<f32>,
+    // None hits within the constant tolerance. Some(e) hits within e times
+    // the distance along the ray, i.e. a constant angle and so about the
+    // same fraction of a cell near and far; meant for sphere tracing, as
+    // fixed steps can stride over a threshold smaller than themselves
+    pub base_eps: Option<f32>,
+}
+
+impl Default for RaymarchConfig {
+    fn default() -> Self {
+        Self { max_steps: 1000, min_step: None, base_eps: None }
+    }
+}
+
+impl RaymarchConfig {
+    /// Hit threshold at distance `dist` along the ray.
+    pub fn epsilon(&self, tolerance: f32, dist: f32) -> f32 {
+        match self.base_eps {
+            Some(base) => (base * dist).max(MIN_EPSILON),
+            None => tolerance,
+        }
+    }
+}
+
+/// Outcome of marching one ray.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MarchResult {
+    // distance along the ray of the first point closer than the tolerance
+    pub depth: Option<f32>,
+    pub steps: u32,
+    // closest approach to the surface, and how far along the ray it was
+    pub min_distance: f32,
+    pub min_at: f32,
+}
+
+/// How much light gets past a shadow ray that `result` marched from a
+/// surface toward the light, from 0 (blocked) to 1: a contact-hardening soft
+/// shadow. Instead of the minimum distance-to-ray-length ratio it takes the
+/// closest approach to an occluder and *where* along the ray that was, so
+/// the penumbra stays tight where the occluder nearly touches the surface and
+/// widens the further away it floats, like under an area light of angular
+/// size `softness`.
+pub fn soft_shadow(result: &MarchResult, softness: f32) -> f32 {
+    if result.depth.is_some() {
+        return 0.0;
+    }
+    let width = softness * result.min_at;
+    if width <= 0.0 {
+        // closest right at the surface: nothing in the way further out
+        return 1.0;
+    }
+    let x = (result.min_distance / width).clamp(0.0, 1.0);
+    x * x * (3.0 - 2.0 * x)
+}
+
+/// Share of a cell that a ray's surface covers, from its march `result` with
+/// hit `tolerance`: 1 for hits, and for a near miss half at the surface,
+/// fading to nothing a cell's footprint further out. Cells subtend `spread`
+/// units of width per unit along the ray.
+pub fn coverage(result: &MarchResult, tolerance: f32, spread: f32) -> f32 {
+    if result.depth.is_some() {
+        return 1.0;
+    }
+    let footprint = result.min_at * spread;
+    (0.5 * (1.0 - (result.min_distance - tolerance) / footprint)).clamp(0.0, 1.0)
+}
+
+/// One distance evaluation along a marched ray.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MarchStep {
+    // distance along the ray and the point there
+    pub k: f32,
+    pub point: Vec3,
+    // the SDF at that point
+    pub distance: f32,
+    // how far the ray moved on from it, 0 for the hit
+    pub step: f32,
+}
+
+impl MarchStep {
+    /// One tab-separated line: k, x, y, z, distance, step.
+    pub fn to_text(&self) -> String {
+        let p = self.point;
+        format!("{:.4}\t{:.4}\t{:.4}\t{:.4}\t{:.4}\t{:.4}", self.k, p.x, p.y, p.z, self.distance, self.step)
+    }
+}
+
+/// Marches from `ro` along the unit direction `rd` until `sdf` drops below
+/// `tolerance`, the ray passes `far` or the step budget runs out.
+pub fn march<F: Fn(Vec3) -> f32>(
+    ro: Vec3,
+    rd: Vec3,
+    far: f32,
+    tolerance: f32,
+    cfg: &RaymarchConfig,
+    sdf: F,
+) -> MarchResult {
+    march_with(ro, rd, far, tolerance, cfg, sdf, |_| {})
+}
+
+/// `march`, handing every step to `observe` as it goes.
+pub fn march_with<F: Fn(Vec3) -> f32, O: FnMut(MarchStep)>(
+    ro: Vec3,
+    rd: Vec3,
+    far: f32,
+    tolerance: f32,
+    cfg: &RaymarchConfig,
+    sdf: F,
+    mut observe: O,
+) -> MarchResult {
+    let mut k = 0.0_f32;
+    let mut steps = 0;
+    let (mut min_distance, mut min_at) = (f32::INFINITY, 0.0);
+    while k < far && steps < cfg.max_steps {
+        let point = ro.add(rd.mul(k));
+        let d = sdf(point);
+        steps += 1;
+        if d < min_distance {
+            (min_distance, min_at) = (d, k);
+        }
+        if d < cfg.epsilon(tolerance, k) {
+            observe(MarchStep { k, point, distance: d, step: 0.0 });
+            return MarchResult { depth: Some(k), steps, min_distance, min_at };
+        }
+        let step = match cfg.min_step {
+            Some(min_step) => d.max(min_step),
+            // step similar to tube radius; the C++ used fixed inRad steps
+            None => tolerance,
+        };
+        observe(MarchStep { k, point, distance: d, step });
+        k += step;
+    }
+    MarchResult { depth: None, steps, min_distance, min_at }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sdf::sd_sphere;
+
+    fn traced(min_step: f32, base_eps: Option<f32>) -> RaymarchConfig {
+        RaymarchConfig { min_step: Some(min_step), base_eps, ..RaymarchConfig::default() }
+    }
+
+    #[test]
+    fn grazing_ray_finishes_with_a_min_step() {
+        // skims the unit sphere just outside its top
+        let (ro, rd) = (Vec3::new(-3.0, 0.0, 1.0002), Vec3::new(1.0, 0.0, 0.0));
+        let sphere = |p: Vec3| sd_sphere(p, 1.0);
+        let result = march(ro, rd, 6.0, 1e-4, &traced(0.01, None), sphere);
+        assert!(result.steps < 200, "{} steps", result.steps);
+        let crawling = march(ro, rd, 6.0, 1e-4, &traced(1e-6, None), sphere);
+        assert!(crawling.steps > 5 * result.steps, "{} steps", crawling.steps);
+    }
+
+    #[test]
+    fn grazing_miss_covers_part_of_the_cell() {
+        let sphere = |p: Vec3| sd_sphere(p, 1.0);
+        // rays along X at height z past the unit sphere
+        let column = |z: f32| {
+            let ro = Vec3::new(-3.0, 0.0, z);
+            let result = march(ro, Vec3::new(1.0, 0.0, 0.0), 6.0, 1e-3, &traced(0.0, None), sphere);
+            coverage(&result, 1e-3, 0.05)
+        };
+        assert_eq!(column(0.5), 1.0);
+        let grazing = column(1.05);
+        assert!(grazing > 0.0 && grazing < 1.0, "{grazing}");
+        assert!(column(1.02) > grazing);
+        assert_eq!(column(2.0), 0.0);
+    }
+
+    #[test]
+    fn scaled_epsilon_grows_with_distance_and_near_hits_land() {
+        let scaled = traced(0.0, Some(1e-3));
+        for dist in [1.0, 2.0, 5.0, 10.0] {
+            assert!((scaled.epsilon(0.05, dist) - 1e-3 * dist).abs() < 1e-7);
+        }
+        assert_eq!(scaled.epsilon(0.05, 0.0), MIN_EPSILON);
+        assert_eq!(RaymarchConfig::default().epsilon(0.05, 10.0), 0.05);
+
+        // a sphere a small step in front of the camera is still hit on its surface
+        let sphere = |p: Vec3| sd_sphere(p.sub(Vec3::new(0.3, 0.0, 0.0)), 0.2);
+        let result = march(Vec3::default(), Vec3::new(1.0, 0.0, 0.0), 6.0, 1e-4, &scaled, sphere);
+        let depth = result.depth.expect("the near sphere is hit");
+        assert!((depth - 0.1).abs() < 1e-3, "{depth}");
+    }
+
+    #[test]
+    fn penumbra_widens_with_the_occluders_distance() {
+        // how far past an occluder's edge the shadow ray must pass to be fully lit
+        let penumbra = |min_at: f32| {
+            let miss = |min_distance| MarchResult { depth: None, steps: 1, min_distance, min_at };
+            (1..=1000).map(|k| k as f32 * 1e-3).find(|&d| soft_shadow(&miss(d), 0.2) >= 1.0).unwrap()
+        };
+        let (near, far) = (penumbra(0.5), penumbra(2.0));
+        assert!((near - 0.1).abs() < 2e-3 && (far - 0.4).abs() < 2e-3, "{near} {far}");
+        // blocked is dark wherever the blocker is
+        let blocked = MarchResult { depth: Some(1.0), steps: 1, min_distance: 0.0, min_at: 1.0 };
+        assert_eq!(soft_shadow(&blocked, 0.2), 0.0);
+    }
+
+    #[test]
+    fn step_log_closes_in_on_a_sphere() {
+        let sphere = |p: Vec3| sd_sphere(p, 1.0);
+        let (ro, rd) = (Vec3::new(-4.0, 0.0, 0.2), Vec3::new(1.0, 0.0, 0.0));
+        let mut log = Vec::new();
+        let result = march_with(ro, rd, 8.0, 1e-4, &traced(0.0, None), sphere, |s| log.push(s));
+        let depth = result.depth.expect("the ray hits the sphere");
+        assert_eq!(log.len() as u32, result.steps);
+        assert!(log.windows(2).all(|w| w[1].distance < w[0].distance && w[1].k > w[0].k));
+        // the last entry is the hit, which doesn't step on
+        let last = log.last().unwrap();
+        assert!(last.distance < 1e-4 && last.step == 0.0 && last.k == depth);
+        assert_eq!(last.to_text().split('\t').count(), 6);
+    }
+}
@@ -0,0 +1,335 @@
+use std::ops::Mul;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vec2 {
+    pub x: f32,
+    pub y: f32,
+}
+impl Vec2 {
+    pub fn new(x: f32, y: f32) -> Self { Self { x, y } }
+}
+
+/// The same public `x`, `y` and `z` fields with or without the `glam`
+/// feature; with it, the arithmetic below runs on `glam::Vec3A` lanes.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vec3 {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+impl Vec3 {
+    pub fn len(self) -> f32 { self.dot(self).sqrt() }
+    pub fn norm(self) -> Self {
+        let l = self.len();
+        if l > 0.0 { self.mul(1.0 / l) } else { self }
+    }
+    /// Mirrors the direction about the unit normal `n`.
+    pub fn reflect(self, n: Vec3) -> Self { self.sub(n.mul(2.0 * self.dot(n))) }
+    /// Bends the unit direction through a surface with unit normal `n`
+    /// (facing against it) by Snell's law, `eta` being the ratio of the
+    /// refractive indices, outside over inside. None on total internal
+    /// reflection.
+    pub fn refract(self, n: Vec3, eta: f32) -> Option<Self> {
+        let cos_i = -self.dot(n);
+        let k = 1.0 - eta * eta * (1.0 - cos_i * cos_i);
+        (k >= 0.0).then(|| self.mul(eta).add(n.mul(eta * cos_i - k.sqrt())))
+    }
+}
+
+// method-style ops keep the ray-march code close to the original C++
+#[allow(clippy::should_implement_trait)]
+impl Vec3 {
+    pub const fn new(x: f32, y: f32, z: f32) -> Self { Self { x, y, z } }
+}
+
+#[cfg(not(feature = "glam"))]
+#[allow(clippy::should_implement_trait)]
+impl Vec3 {
+    pub fn add(self, o: Vec3) -> Self { Self::new(self.x + o.x, self.y + o.y, self.z + o.z) }
+    pub fn sub(self, o: Vec3) -> Self { Self::new(self.x - o.x, self.y - o.y, self.z - o.z) }
+    pub fn mul(self, s: f32) -> Self { Self::new(self.x * s, self.y * s, self.z * s) }
+    pub fn dot(self, o: Vec3) -> f32 { self.x * o.x + self.y * o.y + self.z * o.z }
+    pub fn abs(self) -> Self { Self::new(self.x.abs(), self.y.abs(), self.z.abs()) }
+    pub fn cross(self, o: Vec3) -> Self {
+        Self::new(
+            self.y * o.z - self.z * o.y,
+            self.z * o.x - self.x * o.z,
+            self.x * o.y - self.y * o.x,
+        )
+    }
+}
+
+// the same ops on the SIMD lanes; only these methods forward to glam, so
+// the public API doesn't change with the feature
+#[cfg(feature = "glam")]
+#[allow(clippy::should_implement_trait)]
+impl Vec3 {
+    fn lanes(self) -> glam::Vec3A { glam::Vec3A::new(self.x, self.y, self.z) }
+    fn from_lanes(v: glam::Vec3A) -> Self { Self::new(v.x, v.y, v.z) }
+    pub fn add(self, o: Vec3) -> Self { Self::from_lanes(self.lanes() + o.lanes()) }
+    pub fn sub(self, o: Vec3) -> Self { Self::from_lanes(self.lanes() - o.lanes()) }
+    pub fn mul(self, s: f32) -> Self { Self::from_lanes(self.lanes() * s) }
+    pub fn dot(self, o: Vec3) -> f32 { self.lanes().dot(o.lanes()) }
+    pub fn abs(self) -> Self { Self::from_lanes(self.lanes().abs()) }
+    pub fn cross(self, o: Vec3) -> Self { Self::from_lanes(self.lanes().cross(o.lanes())) }
+}
+
+/// GLSL-style linear blend: `a` at `t = 0`, `b` at `t = 1`.
+pub fn mix(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+/// Two unit vectors that, together with the unit vector `n`, form an
+/// orthonormal basis.
+pub fn orthonormal_basis(n: Vec3) -> (Vec3, Vec3) {
+    // cross with whichever world axis is least aligned with n
+    let helper = if n.x.abs() < 0.9 { Vec3::new(1.0, 0.0, 0.0) } else { Vec3::new(0.0, 1.0, 0.0) };
+    let a = n.cross(helper).norm();
+    (a, n.cross(a))
+}
+
+/// Rotates `v` by `angle_rad` around the unit vector `axis` (Rodrigues).
+pub fn rotate_around_axis(v: Vec3, axis: Vec3, angle_rad: f32) -> Vec3 {
+    let (s, c) = angle_rad.sin_cos();
+    v.mul(c)
+        .add(axis.cross(v).mul(s))
+        .add(axis.mul(axis.dot(v) * (1.0 - c)))
+}
+
+// Named after the C++ euler angle it ports; in this crate's axes it is a
+// rotation about X, i.e. `Mat3::rotation_x`.
+pub fn rot_z(v: Vec3, angle_rad: f32) -> Vec3 {
+    let (s, c) = angle_rad.sin_cos();
+    // rotate the Y–Z plane like original AZ quaternion-from-euler(z)
+    // (their camera used X forward; we keep X as-is, rotate (y,z))
+    Vec3::new(
+        v.x,
+        c * v.y - s * v.z,
+        s * v.y + c * v.z,
+    )
+}
+
+/// Unit quaternion `w + v` for orienting objects.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Quat {
+    pub w: f32,
+    pub v: Vec3,
+}
+
+impl Quat {
+    pub fn identity() -> Self { Self { w: 1.0, v: Vec3::default() } }
+
+    /// Rotation by `angle_rad` around the unit vector `axis`.
+    pub fn from_axis_angle(axis: Vec3, angle_rad: f32) -> Self {
+        let (s, c) = (angle_rad * 0.5).sin_cos();
+        Self { w: c, v: axis.mul(s) }
+    }
+
+    /// The inverse rotation.
+    pub fn conjugate(self) -> Self { Self { w: self.w, v: self.v.mul(-1.0) } }
+
+    pub fn rotate(self, p: Vec3) -> Vec3 {
+        // p + 2w (v x p) + 2 v x (v x p)
+        let t = self.v.cross(p).mul(2.0);
+        p.add(t.mul(self.w)).add(self.v.cross(t))
+    }
+}
+
+impl Default for Quat {
+    fn default() -> Self { Self::identity() }
+}
+
+/// Row-major 3x3 matrix for composing rotations.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mat3 {
+    pub rows: [Vec3; 3],
+}
+
+impl Mat3 {
+    pub fn from_rows(r0: Vec3, r1: Vec3, r2: Vec3) -> Self { Self { rows: [r0, r1, r2] } }
+
+    pub fn identity() -> Self {
+        Self::from_rows(
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        )
+    }
+
+    pub fn rotation_x(angle_rad: f32) -> Self {
+        let (s, c) = angle_rad.sin_cos();
+        Self::from_rows(
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, c, -s),
+            Vec3::new(0.0, s, c),
+        )
+    }
+
+    pub fn rotation_y(angle_rad: f32) -> Self {
+        let (s, c) = angle_rad.sin_cos();
+        Self::from_rows(
+            Vec3::new(c, 0.0, s),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(-s, 0.0, c),
+        )
+    }
+
+    pub fn rotation_z(angle_rad: f32) -> Self {
+        let (s, c) = angle_rad.sin_cos();
+        Self::from_rows(
+            Vec3::new(c, -s, 0.0),
+            Vec3::new(s, c, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+        )
+    }
+
+    /// Rotation by `angle_rad` around the unit vector `axis` (Rodrigues).
+    pub fn from_axis_angle(axis: Vec3, angle_rad: f32) -> Self {
+        let (s, c) = angle_rad.sin_cos();
+        let (x, y, z) = (axis.x, axis.y, axis.z);
+        let t = 1.0 - c;
+        Self::from_rows(
+            Vec3::new(c + x * x * t, x * y * t - z * s, x * z * t + y * s),
+            Vec3::new(y * x * t + z * s, c + y * y * t, y * z * t - x * s),
+            Vec3::new(z * x * t - y * s, z * y * t + x * s, c + z * z * t),
+        )
+    }
+
+    pub fn col(self, i: usize) -> Vec3 {
+        let [r0, r1, r2] = self.rows;
+        match i {
+            0 => Vec3::new(r0.x, r1.x, r2.x),
+            1 => Vec3::new(r0.y, r1.y, r2.y),
+            _ => Vec3::new(r0.z, r1.z, r2.z),
+        }
+    }
+
+    /// The inverse, for the orthonormal rotations built here.
+    pub fn transpose(self) -> Self {
+        Self::from_rows(self.col(0), self.col(1), self.col(2))
+    }
+}
+
+impl Mul<Vec3> for Mat3 {
+    type Output = Vec3;
+
+    fn mul(self, v: Vec3) -> Vec3 {
+        Vec3::new(self.rows[0].dot(v), self.rows[1].dot(v), self.rows[2].dot(v))
+    }
+}
+
+impl Mul<Mat3> for Mat3 {
+    type Output = Mat3;
+
+    fn mul(self, o: Mat3) -> Mat3 {
+        let row = |r: Vec3| Vec3::new(r.dot(o.col(0)), r.dot(o.col(1)), r.dot(o.col(2)));
+        Mat3::from_rows(row(self.rows[0]), row(self.rows[1]), row(self.rows[2]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sdf::sd_torus;
+
+    const EPS: f32 = 1e-5;
+
+    // plain f32 arithmetic to hold either Vec3 backend against
+    fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+        a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+    }
+
+    fn vec3(a: [f32; 3]) -> Vec3 {
+        Vec3::new(a[0], a[1], a[2])
+    }
+
+    const SAMPLES: [[f32; 3]; 4] = [[1.0, 2.0, 3.0], [-0.5, 0.25, 4.0], [0.3, -1.7, -0.2], [1e-3, 0.0, -2.0]];
+
+    #[test]
+    fn dot_and_norm_match_scalar_math() {
+        for a in SAMPLES {
+            for b in SAMPLES {
+                assert!((vec3(a).dot(vec3(b)) - dot(a, b)).abs() < EPS);
+            }
+            let len = dot(a, a).sqrt();
+            let n = vec3(a).norm();
+            for (got, want) in [n.x, n.y, n.z].into_iter().zip(a.map(|c| c / len)) {
+                assert!((got - want).abs() < EPS);
+            }
+        }
+    }
+
+    #[test]
+    fn sd_torus_matches_scalar_math() {
+        let (major, minor) = (1.2, 0.4);
+        for p in SAMPLES {
+            // project onto the XY plane, out to the major circle
+            let planar = (p[0] * p[0] + p[1] * p[1]).sqrt();
+            let want = ((planar - major).powi(2) + p[2] * p[2]).sqrt() - minor;
+            let got = sd_torus(vec3(p), Vec2::new(major, minor), Vec3::new(0.0, 0.0, 1.0));
+            assert!((got - want).abs() < EPS, "{got} vs {want} at {p:?}");
+        }
+    }
+
+    fn close(a: Vec3, b: Vec3) -> bool {
+        a.sub(b).len() < EPS
+    }
+
+    #[test]
+    fn rotations_match_rot_z_and_identity_is_a_no_op() {
+        for angle in [0.0f32, 0.3, 1.7, -2.4, 6.0] {
+            for v in SAMPLES.map(vec3) {
+                // rot_z turns about this crate's X axis
+                assert!(close(Mat3::rotation_x(angle) * v, rot_z(v, angle)));
+                let (s, c) = angle.sin_cos();
+                let about_z = Vec3::new(c * v.x - s * v.y, s * v.x + c * v.y, v.z);
+                assert!(close(Mat3::rotation_z(angle) * v, about_z));
+                assert_eq!(Mat3::identity() * v, v);
+            }
+            let m = Mat3::rotation_x(angle) * Mat3::rotation_y(0.5);
+            assert_eq!(Mat3::identity() * m, m);
+            assert_eq!(m * Mat3::identity(), m);
+        }
+    }
+
+    #[test]
+    fn axis_angle_composes_like_the_axis_rotations() {
+        let axis = Vec3::new(0.0, 1.0, 0.0);
+        for v in SAMPLES.map(vec3) {
+            assert!(close(Mat3::from_axis_angle(axis, 0.8) * v, Mat3::rotation_y(0.8) * v));
+            let there_and_back = Mat3::rotation_x(0.4).transpose() * Mat3::rotation_x(0.4);
+            assert!(close(there_and_back * v, v));
+        }
+    }
+
+    #[test]
+    fn refraction_obeys_snells_law() {
+        // 45 degrees from the normal into glass, from air
+        let n = Vec3::new(0.0, 0.0, 1.0);
+        let (theta_i, eta) = (std::f32::consts::FRAC_PI_4, 1.0 / 1.5);
+        let d = Vec3::new(theta_i.sin(), 0.0, -theta_i.cos());
+        let t = d.refract(n, eta).expect("air into glass never reflects totally");
+        assert!((t.len() - 1.0).abs() < EPS);
+        // sin(theta_t) = eta sin(theta_i), bending toward the normal in the plane of incidence
+        let sin_t = (t.x * t.x + t.y * t.y).sqrt();
+        assert!((sin_t - eta * theta_i.sin()).abs() < EPS, "{sin_t}");
+        assert!(t.z < 0.0 && t.x > 0.0 && t.y.abs() < EPS);
+        // head-on passes straight through, and past the critical angle nothing does
+        assert!(n.mul(-1.0).refract(n, eta).unwrap().sub(n.mul(-1.0)).len() < EPS);
+        assert_eq!(d.refract(n, 1.5), None);
+    }
+
+    #[test]
+    fn fields_and_literals_work_with_either_backend() {
+        // the public shape is the same with or without the glam feature
+        let mut v = Vec3 { x: 1.0, y: 2.0, z: 3.0 };
+        v.y = -2.0;
+        assert_eq!(v, Vec3::new(1.0, -2.0, 3.0));
+        assert_eq!(v.add(Vec3 { x: 1.0, ..v }).x, 2.0);
+    }
+}
@@ -0,0 +1,715 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crossterm::{
+    cursor::{Hide, MoveTo, MoveToColumn, MoveToPreviousLine, Show},
+    queue,
+    style::{Color, ResetColor, SetForegroundColor},
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen, WindowSize},
+};
+
+use crate::color::{ansi256_rgb, rgb_to_ansi256, to_rgb, Theme};
+use crate::render::Frame;
+
+/// Where frames are drawn.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Screen {
+    // take over the terminal, restoring its contents on exit
+    #[default]
+    Alternate,
+    // draw in place below the prompt and leave the last frame in scrollback
+    Inline,
+}
+
+/// What ends each row of plain text output.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    pub const ALL: [LineEnding; 2] = [LineEnding::Lf, LineEnding::Crlf];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "lf",
+            LineEnding::Crlf => "crlf",
+        }
+    }
+
+    pub fn terminator(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+        }
+    }
+}
+
+impl std::str::FromStr for LineEnding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LineEnding::ALL
+            .into_iter()
+            .find(|e| e.name() == s)
+            .ok_or_else(|| format!("unknown line ending: {s} (expected lf or crlf)"))
+    }
+}
+
+// glyphs and colors of a drawn frame, with its (width, height)
+pub type Cells = (Vec<u8>, Vec<Color>, (u16, u16));
+
+impl Screen {
+    pub fn setup<W: Write>(self, out: &mut W) -> io::Result<()> {
+        match self {
+            Screen::Alternate => queue!(out, EnterAlternateScreen, Hide)?,
+            Screen::Inline => {}
+        }
+        out.flush()
+    }
+
+    pub fn teardown<W: Write>(self, out: &mut W) -> io::Result<()> {
+        match self {
+            Screen::Alternate => queue!(out, Show, LeaveAlternateScreen)?,
+            Screen::Inline => {
+                queue!(out, ResetColor, Show)?;
+                // step below the last frame so the prompt doesn't overwrite it
+                write!(out, "\r\n")?;
+            }
+        }
+        out.flush()
+    }
+
+    /// Tears down like `teardown`, then for the alternate screen, which
+    /// takes its frames with it, draws `last` again below the prompt so the
+    /// final pose stays in the scrollback.
+    pub fn teardown_persisting<W: Write>(
+        self,
+        out: &mut W,
+        last: Option<&Cells>,
+        colored: bool,
+        background: char,
+    ) -> io::Result<()> {
+        self.teardown(out)?;
+        if let Some((chars, colors, (width, height))) = last.filter(|_| self == Screen::Alternate) {
+            write_frame(out, chars, colors, *width, *height, Screen::Inline, colored, background, None)?;
+            writeln!(out)?;
+        }
+        out.flush()
+    }
+
+    /// Moves back to the first row of the previous inline frame so the next
+    /// one overdraws it. The alternate screen addresses rows absolutely.
+    pub fn rewind<W: Write>(self, out: &mut W, height: u16) -> io::Result<()> {
+        if self == Screen::Inline {
+            if height > 1 {
+                queue!(out, MoveToPreviousLine(height - 1))?;
+            }
+            queue!(out, MoveToColumn(0))?;
+        }
+        Ok(())
+    }
+}
+
+// glyph byte of background cells that `write_frame` draws as the chosen
+// background character, which needn't fit in a byte
+pub const BACKGROUND: u8 = 0;
+
+/// Marks every empty background cell of `frame` (no hit, nothing drawn) as
+/// `BACKGROUND` in `color`.
+pub fn mark_background(frame: &mut Frame, color: Color) {
+    for idx in 0..frame.chars.len() {
+        if frame.chars[idx] == b' ' && !frame.depth[idx].is_finite() {
+            frame.chars[idx] = BACKGROUND;
+            frame.colors[idx] = color;
+        }
+    }
+}
+
+/// Writes one frame, emitting `SetForegroundColor` only when the color
+/// actually changes. The terminal keeps the last color across cursor moves,
+/// so the tracking carries over from one row to the next. Without `colored`
+/// only the glyphs and cursor movement are written, and with a `text`
+/// ending as well every row is followed by that instead of being moved to,
+/// for a plain text file. Cells marked `BACKGROUND` show `background`.
+#[allow(clippy::too_many_arguments)]
+pub fn write_frame<W: Write>(
+    out: &mut W,
+    frame_buf: &[u8],
+    color_buf: &[Color],
+    width: u16,
+    height: u16,
+    screen: Screen,
+    colored: bool,
+    background: char,
+    text: Option<LineEnding>,
+) -> io::Result<()> {
+    let mut emitted: Option<Color> = None;
+    let mut run = String::new();
+    let text = text.filter(|_| !colored);
+
+    for j in 0..height {
+        // Move to start of each line
+        match screen {
+            _ if text.is_some() => {}
+            Screen::Alternate => queue!(out, MoveTo(0, j))?,
+            // raw mode doesn't translate \n, so return the carriage too
+            Screen::Inline if j > 0 => write!(out, "\r\n")?,
+            Screen::Inline => {}
+        }
+
+        for i in 0..width {
+            let idx = (i as usize) + (j as usize) * (width as usize);
+            let color = color_buf[idx];
+
+            if colored && emitted != Some(color) {
+                // Flush the run drawn in the previous color first
+                if !run.is_empty() {
+                    write!(out, "{run}")?;
+                    run.clear();
+                }
+                queue!(out, SetForegroundColor(color))?;
+                emitted = Some(color);
+            }
+
+            run.push(if frame_buf[idx] == BACKGROUND { background } else { frame_buf[idx] as char });
+        }
+
+        // Flush remaining characters for this line
+        if !run.is_empty() {
+            write!(out, "{run}")?;
+            run.clear();
+        }
+        if let Some(ending) = text {
+            out.write_all(ending.terminator().as_bytes())?;
+        }
+    }
+
+    if colored {
+        queue!(out, ResetColor)?;
+    }
+    Ok(())
+}
+
+/// Top-left corner that centers a `render`-sized image in `term`, both as
+/// (width, height). Zero along axes where the image doesn't fit.
+pub fn center_offset(render: (u16, u16), term: (u16, u16)) -> (u16, u16) {
+    (term.0.saturating_sub(render.0) / 2, term.1.saturating_sub(render.1) / 2)
+}
+
+/// Largest viewport inside `term` (width, height) that is as wide as it is
+/// tall once each cell's `pixel_aspect` (width over height) is taken into
+/// account. At least one cell each way. The rays already correct for the
+/// cell aspect, so the donut is round either way: the lock only letterboxes
+/// it into a square frame.
+pub fn locked_viewport(term: (u16, u16), pixel_aspect: f32) -> (u16, u16) {
+    let (w, h) = (term.0 as f32, term.1 as f32);
+    if w * pixel_aspect > h {
+        (((h / pixel_aspect).round() as u16).clamp(1, term.0), term.1.max(1))
+    } else {
+        (term.0.max(1), ((w * pixel_aspect).round() as u16).clamp(1, term.1))
+    }
+}
+
+/// Cell aspect (width over height) from the terminal's size in pixels and
+/// in cells, as `window_size` reports it. None where the terminal leaves the
+/// pixel size at zero, as many do.
+pub fn cell_aspect(size: WindowSize) -> Option<f32> {
+    let WindowSize { rows, columns, width, height } = size;
+    if rows == 0 || columns == 0 || width == 0 || height == 0 {
+        return None;
+    }
+    Some((width as f32 / columns as f32) / (height as f32 / rows as f32))
+}
+
+// how long to wait for the terminal to answer the size queries
+pub const QUERY_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// The two numbers of a window report at the start of `reply`, the answer
+/// to `CSI 14 t` (`ESC [ 4 ; height ; width t`, the text area in pixels) or
+/// to `CSI 18 t` (`ESC [ 8 ; rows ; columns t`), both height first. None
+/// for any other, truncated or zero report.
+pub fn parse_window_report(reply: &[u8]) -> Option<(u16, u16)> {
+    let body = reply.strip_prefix(b"\x1b[")?;
+    let end = body.iter().position(|&b| b == b't')?;
+    let text = std::str::from_utf8(&body[..end]).ok()?;
+    let fields: Vec<u16> = text.split(';').map(str::parse).collect::<Result<_, _>>().ok()?;
+    match fields[..] {
+        [4 | 8, h, w] if h > 0 && w > 0 => Some((h, w)),
+        _ => None,
+    }
+}
+
+/// The window size from everything the terminal sent back to the size
+/// queries, the reports in any order and anything else skipped. None unless
+/// both reports are there.
+pub fn window_size_from_replies(reply: &[u8]) -> Option<WindowSize> {
+    let (mut pixels, mut cells) = (None, None);
+    for start in (0..reply.len()).filter(|&i| reply[i] == 0x1b) {
+        let report = &reply[start..];
+        match (report.get(2), parse_window_report(report)) {
+            (Some(b'4'), Some(size)) => pixels = Some(size),
+            (Some(b'8'), Some(size)) => cells = Some(size),
+            _ => {}
+        }
+    }
+    let ((height, width), (rows, columns)) = (pixels?, cells?);
+    Some(WindowSize { rows, columns, width, height })
+}
+
+/// Asks the terminal, which must be in raw mode, for its size in pixels and
+/// in cells, for where `window_size` leaves the pixels at zero. A trailing
+/// status query (`CSI 5 n`), which nearly every terminal answers, marks the
+/// end of the replies. None when nothing usable came back within `timeout`.
+pub fn query_window_size<W: Write>(out: &mut W, timeout: Duration) -> io::Result<Option<WindowSize>> {
+    write!(out, "\x1b[14t\x1b[18t\x1b[5n")?;
+    out.flush()?;
+    let (tx, rx) = mpsc::channel();
+    let late = Arc::new(AtomicBool::new(false));
+    // stdin can't time out, so a thread reads it; it stops at the status
+    // reply, the only `n` of all three, or at the first byte after the
+    // timeout, so a silent terminal costs at most one byte of key input
+    let reading = Arc::clone(&late);
+    thread::spawn(move || {
+        let mut reply = Vec::new();
+        for byte in io::stdin().lock().bytes() {
+            let Ok(byte) = byte else { break };
+            if reading.load(Ordering::Relaxed) {
+                break;
+            }
+            reply.push(byte);
+            if byte == b'n' {
+                break;
+            }
+        }
+        let _ = tx.send(reply);
+    });
+    let reply = rx.recv_timeout(timeout).ok();
+    late.store(true, Ordering::Relaxed);
+    Ok(reply.and_then(|reply| window_size_from_replies(&reply)))
+}
+
+/// Source column (or row) for destination cell `x` when scaling `from`
+/// cells onto `to`, nearest neighbour by cell centers.
+pub fn source_cell(x: u16, from: u16, to: u16) -> u16 {
+    let x = ((x as u32 * 2 + 1) * from as u32 / (to as u32 * 2)) as u16;
+    x.min(from.saturating_sub(1))
+}
+
+/// Nearest-neighbour rescale of `from`-sized glyph and color buffers to
+/// `to`, both as (width, height).
+pub fn scale_nearest(
+    chars: &[u8],
+    colors: &[Color],
+    from: (u16, u16),
+    to: (u16, u16),
+) -> (Vec<u8>, Vec<Color>) {
+    let cells = to.0 as usize * to.1 as usize;
+    let (mut out_chars, mut out_colors) = (Vec::with_capacity(cells), Vec::with_capacity(cells));
+    for j in 0..to.1 {
+        let row = source_cell(j, from.1, to.1) as usize * from.0 as usize;
+        for i in 0..to.0 {
+            let idx = row + source_cell(i, from.0, to.0) as usize;
+            out_chars.push(chars[idx]);
+            out_colors.push(colors[idx]);
+        }
+    }
+    (out_chars, out_colors)
+}
+
+/// Glyph and color buffers of `term` size with the `render`-sized ones
+/// centered inside, the border filled with blank background cells.
+pub fn letterbox(
+    chars: &[u8],
+    colors: &[Color],
+    render: (u16, u16),
+    term: (u16, u16),
+) -> (Vec<u8>, Vec<Color>) {
+    let (x0, y0) = center_offset(render, term);
+    let cells = term.0 as usize * term.1 as usize;
+    let (mut out_chars, mut out_colors) = (vec![b' '; cells], vec![Color::Reset; cells]);
+    for j in 0..render.1.min(term.1) as usize {
+        let src = j * render.0 as usize;
+        let dst = x0 as usize + (y0 as usize + j) * term.0 as usize;
+        let n = render.0.min(term.0) as usize;
+        out_chars[dst..dst + n].copy_from_slice(&chars[src..src + n]);
+        out_colors[dst..dst + n].copy_from_slice(&colors[src..src + n]);
+    }
+    (out_chars, out_colors)
+}
+
+/// Sixel image of `colors`, `size` cells (width, height) row-major, with
+/// every cell a `block` x `block` square of pixels. Colors go through the
+/// 256-color palette, and only the registers that are used get declared.
+/// Nothing moves the cursor; the image starts wherever it is.
+pub fn write_sixel<W: Write>(out: &mut W, colors: &[Color], size: (u16, u16), block: u16) -> io::Result<()> {
+    let block = block.max(1) as usize;
+    let (w, h) = (size.0 as usize, size.1 as usize);
+    let (pw, ph) = (w * block, h * block);
+    let index: Vec<u8> = colors
+        .iter()
+        .map(|&c| {
+            let (r, g, b) = to_rgb(c);
+            rgb_to_ansi256(r, g, b)
+        })
+        .collect();
+
+    // square pixels and the raster size, then the palette in percent
+    write!(out, "\x1bP0;1;0q\"1;1;{pw};{ph}")?;
+    let mut used = [false; 256];
+    for &i in &index {
+        used[i as usize] = true;
+    }
+    let percent = |c: u8| (c as u32 * 100 + 127) / 255;
+    for i in (0..=255).filter(|&i| used[i as usize]) {
+        let (r, g, b) = ansi256_rgb(i);
+        write!(out, "#{i};2;{};{};{}", percent(r), percent(g), percent(b))?;
+    }
+
+    // six pixel rows at a time: one pass per color in the band, each column
+    // a character whose bits are the rows in that color
+    let index = &index;
+    for top in (0..ph).step_by(6) {
+        let rows = top..(top + 6).min(ph);
+        let column = |x: usize| rows.clone().map(move |y| index[x / block + y / block * w]);
+        let mut present = [false; 256];
+        for x in 0..pw {
+            column(x).for_each(|i| present[i as usize] = true);
+        }
+        let mut first = true;
+        for i in (0..=255).filter(|&i| present[i as usize]) {
+            if !first {
+                write!(out, "$")?;
+            }
+            first = false;
+            write!(out, "#{i}")?;
+            let bits: Vec<u8> =
+                (0..pw).map(|x| column(x).enumerate().fold(0, |b, (k, c)| b | (u8::from(c == i) << k))).collect();
+            let mut x = 0;
+            while x < pw {
+                let run = bits[x..].iter().take_while(|&&b| b == bits[x]).count();
+                let c = (63 + bits[x]) as char;
+                if run > 3 {
+                    write!(out, "!{run}{c}")?;
+                } else {
+                    (0..run).try_for_each(|_| write!(out, "{c}"))?;
+                }
+                x += run;
+            }
+        }
+        write!(out, "-")?;
+    }
+    write!(out, "\x1b\\")
+}
+
+/// One line per theme: its name and, when `colored`, a swatch running from
+/// dark to bright.
+pub fn write_theme_list<W: Write>(out: &mut W, colored: bool) -> io::Result<()> {
+    const SWATCH: usize = 8;
+    for theme in Theme::ALL {
+        if !colored {
+            writeln!(out, "{}", theme.name())?;
+            continue;
+        }
+        write!(out, "{:<12} ", theme.name())?;
+        for i in 0..SWATCH {
+            let color = theme.color(i as f32 / (SWATCH - 1) as f32);
+            queue!(out, SetForegroundColor(color))?;
+            write!(out, "\u{2588}\u{2588}")?;
+        }
+        queue!(out, ResetColor)?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// The glyphs of `frame` as plain text, every row followed by `ending`.
+pub fn write_text<W: Write>(out: &mut W, frame: &Frame, ending: LineEnding) -> io::Result<()> {
+    for row in frame.chars.chunks(frame.width as usize) {
+        out.write_all(row)?;
+        out.write_all(ending.terminator().as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Binary PPM (P6) with one pixel per character cell.
+pub fn write_ppm<W: Write>(out: &mut W, frame: &Frame) -> io::Result<()> {
+    write!(out, "P6\n{} {}\n255\n", frame.width, frame.height)?;
+    out.write_all(&rgb_bytes(frame))
+}
+
+/// Writes `frame` to a PPM named after the current time,
+/// `torus-<unix millis>.ppm` in `dir`, and returns the file name.
+pub fn save_snapshot(frame: &Frame, dir: &Path) -> io::Result<String> {
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis());
+    let name = format!("torus-{millis}.ppm");
+    let mut file = BufWriter::new(File::create(dir.join(&name))?);
+    write_ppm(&mut file, frame)?;
+    file.flush()?;
+    Ok(name)
+}
+
+/// The frame's colors as packed RGB24, `width * height * 3` bytes, row-major.
+pub fn rgb_bytes(frame: &Frame) -> Vec<u8> {
+    frame
+        .colors
+        .iter()
+        .flat_map(|&c| {
+            let (r, g, b) = to_rgb(c);
+            [r, g, b]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::render::Renderer;
+
+    #[test]
+    fn cell_aspect_from_pixel_and_cell_size() {
+        // 80x24 cells of 10x20 pixels
+        let size = WindowSize { rows: 24, columns: 80, width: 800, height: 480 };
+        assert_eq!(cell_aspect(size), Some(0.5));
+    }
+
+    #[test]
+    fn silent_terminal_keeps_the_default_aspect() {
+        let silent = || WindowSize { rows: 24, columns: 80, width: 0, height: 0 };
+        assert_eq!(cell_aspect(silent()), None);
+        let renderer = Renderer::new(&Config::default());
+        assert_eq!(cell_aspect(silent()).unwrap_or(renderer.pixel_aspect), 11.0 / 24.0);
+    }
+
+    #[test]
+    fn locked_viewport_boxes_extreme_terminals() {
+        // cells twice as tall as wide: a square is twice as many columns as rows
+        assert_eq!(locked_viewport((80, 24), 0.5), (48, 24));
+        assert_eq!(locked_viewport((1000, 10), 0.5), (20, 10));
+        assert_eq!(locked_viewport((10, 1000), 0.5), (10, 5));
+        assert_eq!(locked_viewport((1, 500), 0.5), (1, 1));
+        assert_eq!(locked_viewport((500, 1), 0.5), (2, 1));
+        // the box is square in pixels and never leaves the terminal
+        for term in [(300, 3), (3, 300), (120, 40), (7, 7)] {
+            for aspect in [0.3, 11.0 / 24.0, 1.0, 2.0] {
+                let (w, h) = locked_viewport(term, aspect);
+                assert!(w <= term.0 && h <= term.1 && (w == term.0 || h == term.1));
+                assert!((w as f32 * aspect - h as f32).abs() <= aspect.max(1.0));
+            }
+        }
+    }
+
+    fn written(frame: &[u8], colors: &[Color], width: u16, height: u16, colored: bool) -> String {
+        let mut out = Vec::new();
+        write_frame(&mut out, frame, colors, width, height, Screen::Inline, colored, ' ', None).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn row_of_one_color_emits_one_escape() {
+        let red = Color::Rgb { r: 255, g: 0, b: 0 };
+        let out = written(b"@@@@@@@@", &[red; 8], 8, 1, true);
+        assert_eq!(out.matches("\x1b[38;").count(), 1);
+        assert!(out.contains("@@@@@@@@"));
+        // and one more per change of color
+        let blue = Color::Rgb { r: 0, g: 0, b: 255 };
+        let out = written(b"@@@@@@@@", &[red, red, blue, blue, blue, red, red, red], 8, 1, true);
+        assert_eq!(out.matches("\x1b[38;").count(), 3);
+    }
+
+    fn sequence(write: impl FnOnce(&mut Vec<u8>) -> io::Result<()>) -> String {
+        let mut out = Vec::new();
+        write(&mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn inline_mode_skips_the_alternate_screen() {
+        let alternate = Config::default().screen();
+        let inline = Config::from_args(["--no-alt-screen".to_string()]).unwrap().screen();
+        assert_eq!((alternate, inline), (Screen::Alternate, Screen::Inline));
+        assert_eq!(sequence(|out| alternate.setup(out)), "\x1b[?1049h\x1b[?25l");
+        assert_eq!(sequence(|out| alternate.teardown(out)), "\x1b[?25h\x1b[?1049l");
+        assert_eq!(sequence(|out| inline.setup(out)), "");
+        let teardown = sequence(|out| inline.teardown(out));
+        assert!(!teardown.contains("1049") && teardown.ends_with("\x1b[?25h\r\n"));
+    }
+
+    #[test]
+    fn rgb_frame_is_three_bytes_per_cell() {
+        let mut frame = Frame::new(7, 3);
+        frame.colors[1] = Color::Rgb { r: 1, g: 2, b: 3 };
+        let bytes = rgb_bytes(&frame);
+        assert_eq!(bytes.len(), 7 * 3 * 3);
+        assert_eq!(bytes[3..6], [1, 2, 3]);
+    }
+
+    #[test]
+    fn theme_list_names_every_theme_once() {
+        let expected: Vec<&str> = Theme::ALL.iter().map(|theme| theme.name()).collect();
+        let colored = sequence(|out| write_theme_list(out, true));
+        let names: Vec<&str> = colored.lines().filter_map(|line| line.split_whitespace().next()).collect();
+        assert_eq!(names, expected);
+        // without color it is the bare names, nothing but ASCII
+        let plain = sequence(|out| write_theme_list(out, false));
+        assert_eq!(plain.lines().collect::<Vec<_>>(), expected);
+        assert!(plain.is_ascii());
+    }
+
+    #[test]
+    fn uncolored_frame_has_no_sgr_escapes() {
+        let colors = [Color::Rgb { r: 255, g: 0, b: 0 }, Color::Rgb { r: 0, g: 0, b: 255 }].repeat(6);
+        let out = written(b"@@..@@  .@@.", &colors, 6, 2, false);
+        // only cursor movement is left
+        assert!(!out.contains('m'), "{out:?}");
+        assert!(out.is_ascii());
+        assert!(out.contains("@@..@@"));
+    }
+
+    #[test]
+    fn capped_render_is_centered_in_the_terminal() {
+        assert_eq!(center_offset((120, 40), (200, 60)), (40, 10));
+        // odd margins leave the extra column and row on the far side
+        assert_eq!(center_offset((80, 24), (81, 27)), (0, 1));
+        assert_eq!(center_offset((80, 24), (80, 24)), (0, 0));
+        // a render larger than the terminal starts at the corner
+        assert_eq!(center_offset((100, 30), (80, 24)), (0, 0));
+    }
+
+    #[test]
+    fn canvas_cells_map_to_the_nearest_terminal_cells() {
+        let map = |from, to| (0..to).map(|x| source_cell(x, from, to)).collect::<Vec<_>>();
+        // doubling repeats each cell, halving keeps the second of each pair
+        assert_eq!(map(2, 4), [0, 0, 1, 1]);
+        assert_eq!(map(4, 2), [1, 3]);
+        assert_eq!(map(3, 2), [0, 2]);
+        assert_eq!(map(5, 5), [0, 1, 2, 3, 4]);
+
+        let red = Color::Rgb { r: 255, g: 0, b: 0 };
+        let (chars, colors) = scale_nearest(b"abcd", &[red, red, Color::Reset, red], (2, 2), (4, 1));
+        assert_eq!(chars, b"ccdd");
+        assert_eq!(colors, [Color::Reset, Color::Reset, red, red]);
+    }
+
+    #[test]
+    fn snapshot_is_a_ppm_of_the_current_frame() {
+        let mut frame = Frame::new(7, 3);
+        frame.colors[4] = Color::Rgb { r: 9, g: 8, b: 7 };
+        let dir = std::env::temp_dir();
+        let name = save_snapshot(&frame, &dir).unwrap();
+        let path = dir.join(&name);
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(name.starts_with("torus-") && name.ends_with(".ppm"));
+        let header = b"P6\n7 3\n255\n";
+        assert_eq!(&bytes[..header.len()], header);
+        assert_eq!(bytes.len(), header.len() + 7 * 3 * 3);
+        assert_eq!(&bytes[header.len() + 12..header.len() + 15], [9, 8, 7]);
+    }
+
+    #[test]
+    fn sixel_of_two_cells_has_header_palette_and_bands() {
+        let (red, blue) = (Color::Rgb { r: 255, g: 0, b: 0 }, Color::Rgb { r: 0, g: 0, b: 255 });
+        let out = sequence(|out| write_sixel(out, &[red, blue], (2, 1), 3));
+        // DCS with square pixels and a 6x3 raster, then the two registers used
+        let header = "\x1bP0;1;0q\"1;1;6;3#21;2;0;0;100#196;2;100;0;0";
+        assert!(out.starts_with(header), "{out:?}");
+        // one band: blue lights the right three columns' three rows, red the left
+        assert_eq!(&out[header.len()..], "#21???FFF$#196FFF???-\x1b\\");
+    }
+
+    #[test]
+    fn empty_cells_show_the_background_glyph() {
+        // nothing hit anywhere but one cell
+        let mut frame = Frame::new(4, 2);
+        frame.chars[5] = b'@';
+        frame.depth[5] = 2.0;
+        let gray = Color::Rgb { r: 60, g: 60, b: 60 };
+        mark_background(&mut frame, gray);
+        assert_eq!(frame.chars.iter().filter(|&&c| c == BACKGROUND).count(), 7);
+        assert_eq!(frame.colors[0], gray);
+        assert_eq!(frame.colors[5], Color::Reset);
+
+        let (chars, colors) = (&frame.chars, &frame.colors);
+        let out = sequence(|out| write_frame(out, chars, colors, 4, 2, Screen::Inline, false, '·', None));
+        assert_eq!(out.matches('·').count(), 7);
+        assert!(out.contains("·@··"));
+    }
+
+    #[test]
+    fn persisting_teardown_redraws_the_last_frame_on_the_main_screen() {
+        let cells: Cells = (b"@@..".to_vec(), vec![Color::Reset; 4], (2, 2));
+        let last = Some(&cells);
+        let out = sequence(|out| Screen::Alternate.teardown_persisting(out, last, false, ' '));
+        let leave = "\x1b[?25h\x1b[?1049l";
+        assert!(out.starts_with(leave), "{out:?}");
+        assert!(out[leave.len()..].contains("@@") && out.contains(".."));
+        // without a frame, or inline where it is still on screen, it is a plain teardown
+        let plain = sequence(|out| Screen::Alternate.teardown(out));
+        assert_eq!(sequence(|out| Screen::Alternate.teardown_persisting(out, None, false, ' ')), plain);
+        let inline = sequence(|out| Screen::Inline.teardown(out));
+        assert_eq!(sequence(|out| Screen::Inline.teardown_persisting(out, last, false, ' ')), inline);
+    }
+
+    #[test]
+    fn headless_text_ends_every_row_with_the_chosen_terminator() {
+        let mut frame = Frame::new(12, 5);
+        Renderer::new(&Config::default()).render(0, &mut frame);
+        let lf = sequence(|out| write_text(out, &frame, LineEnding::Lf));
+        assert_eq!(lf.matches('\n').count(), 5);
+        assert!(!lf.contains('\r'));
+        let crlf = sequence(|out| write_text(out, &frame, LineEnding::Crlf));
+        assert_eq!(crlf.matches("\r\n").count(), 5);
+        // no bare newlines besides the terminators
+        assert_eq!(crlf.matches('\n').count(), 5);
+        assert_eq!(crlf.len(), 12 * 5 + 2 * 5);
+    }
+
+    #[test]
+    fn window_reports_parse_from_sample_replies() {
+        // xterm's answers to CSI 14 t and CSI 18 t
+        assert_eq!(parse_window_report(b"\x1b[4;480;800t"), Some((480, 800)));
+        assert_eq!(parse_window_report(b"\x1b[8;24;80t\x1b[0n"), Some((24, 80)));
+        // truncated, garbage, the wrong report and a zero size
+        let bad = [&b"\x1b[4;480;80"[..], b"\x1b[8;24t", b"\x1b[4;a;800t", b"4;480;800t", b"\x1b[3;1;2t"];
+        for reply in bad.into_iter().chain([&b"\x1b[4;0;800t"[..], b"\x1b[8;24;99999t", b""]) {
+            assert_eq!(parse_window_report(reply), None, "{reply:?}");
+        }
+        // both reports in either order around the status reply, 10x20 pixel cells
+        let aspect = |reply: &[u8]| window_size_from_replies(reply).and_then(cell_aspect);
+        assert_eq!(aspect(b"\x1b[4;480;800t\x1b[8;24;80t\x1b[0n"), Some(0.5));
+        assert_eq!(aspect(b"x\x1b[8;24;80t\x1b[4;480;800t"), Some(0.5));
+        assert_eq!(aspect(b"\x1b[8;24;80t\x1b[0n"), None);
+        assert_eq!(aspect(b"\x1b[4;480;800t\x1b[8;24;8"), None);
+    }
+
+    #[test]
+    fn ascii_only_rows_end_with_the_chosen_terminator() {
+        let (chars, colors) = (b"@@..@@  .@@.", [Color::Reset; 12]);
+        for screen in [Screen::Alternate, Screen::Inline] {
+            let text = |ending| {
+                sequence(|out| write_frame(out, chars, &colors, 6, 2, screen, false, ' ', ending))
+            };
+            let lf = text(Some(LineEnding::Lf));
+            assert_eq!(lf, "@@..@@\n  .@@.\n");
+            let crlf = text(Some(LineEnding::Crlf));
+            assert_eq!((crlf.matches("\r\n").count(), crlf.matches('\n').count()), (2, 2));
+            assert!(!crlf.contains('\x1b'));
+        }
+        // colored frames keep placing their rows
+        let colored = sequence(|out| {
+            write_frame(out, chars, &colors, 6, 2, Screen::Alternate, true, ' ', Some(LineEnding::Lf))
+        });
+        assert!(colored.contains("\x1b[2;1H") && !colored.contains('\n'));
+    }
+}
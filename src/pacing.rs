@@ -0,0 +1,49 @@
+use std::time::{Duration, Instant};
+
+/// Frame pacing against a running deadline instead of a fixed sleep, so
+/// render time doesn't slow the animation down.
+#[derive(Copy, Clone, Debug)]
+pub struct Pacer {
+    pub period: Duration,
+    deadline: Instant,
+}
+
+impl Pacer {
+    pub fn new(period: Duration, start: Instant) -> Self {
+        Self { period, deadline: start + period }
+    }
+
+    /// How long to sleep at `now` for the frame that just finished. Running
+    /// late skips the sleep; more than a whole period behind starts a fresh
+    /// deadline rather than rushing frames out to catch up.
+    pub fn wait(&mut self, now: Instant) -> Duration {
+        let wait = self.deadline.saturating_duration_since(now);
+        if now > self.deadline + self.period {
+            self.deadline = now + self.period;
+        } else {
+            self.deadline += self.period;
+        }
+        wait
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sleeps_off_the_rest_of_the_period_and_skips_when_late() {
+        let ms = Duration::from_millis;
+        let start = Instant::now();
+        let mut pacer = Pacer::new(ms(16), start);
+        // a 4 ms render sleeps the other 12
+        assert_eq!(pacer.wait(start + ms(4)), ms(12));
+        // the next deadline is 32 ms: 20 ms of render leaves nothing to sleep
+        assert_eq!(pacer.wait(start + ms(36)), Duration::ZERO);
+        // still on the running deadline of 48 ms, so the next frame catches up
+        assert_eq!(pacer.wait(start + ms(40)), ms(8));
+        // far behind, the deadline starts afresh a period from now
+        assert_eq!(pacer.wait(start + ms(200)), Duration::ZERO);
+        assert_eq!(pacer.wait(start + ms(210)), ms(6));
+    }
+}
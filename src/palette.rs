@@ -0,0 +1,190 @@
+use crossterm::style::Color;
+
+use crate::color::{parse_hex, to_hex, to_rgb};
+use crate::error::DonutError;
+use crate::input::Action;
+use crate::render::Frame;
+
+// channel change per arrow press
+pub const CHANNEL_STEP: u8 = 8;
+
+/// Live gradient editing: a selected stop and RGB channel, changed by the
+/// number keys and arrows.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PaletteEditor {
+    pub stops: Vec<(f32, Color)>,
+    pub selected: usize,
+    // 0, 1, 2 for red, green, blue
+    pub channel: usize,
+}
+
+impl PaletteEditor {
+    pub fn new(stops: &[(f32, Color)]) -> Self {
+        Self { stops: stops.to_vec(), selected: 0, channel: 0 }
+    }
+
+    /// Applies `action` and returns whether it was an editing one. Number
+    /// keys past the last stop are ignored; left/right pick the channel,
+    /// up/down nudge it by `CHANNEL_STEP`, saturating.
+    pub fn apply(&mut self, action: Action) -> bool {
+        match action {
+            Action::SelectStop(k) => {
+                if k < self.stops.len() {
+                    self.selected = k;
+                }
+            }
+            Action::LightLeft => self.channel = (self.channel + 2) % 3,
+            Action::LightRight => self.channel = (self.channel + 1) % 3,
+            Action::LightUp | Action::LightDown => {
+                let (r, g, b) = to_rgb(self.stops[self.selected].1);
+                let mut rgb = [r, g, b];
+                let c = &mut rgb[self.channel];
+                *c = if action == Action::LightUp {
+                    c.saturating_add(CHANNEL_STEP)
+                } else {
+                    c.saturating_sub(CHANNEL_STEP)
+                };
+                let [r, g, b] = rgb;
+                self.stops[self.selected].1 = Color::Rgb { r, g, b };
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Draws the swatch strip over the top row: each stop's number and a
+    /// block in its color, the selected one as `@`, then the selected
+    /// channel and its value.
+    pub fn draw(&self, frame: &mut Frame) {
+        let white = Color::Rgb { r: 255, g: 255, b: 255 };
+        let mut cells = Vec::new();
+        for (k, &(_, color)) in self.stops.iter().enumerate() {
+            let block = if k == self.selected { b'@' } else { b'#' };
+            cells.push((b'1' + k as u8, white));
+            cells.extend([(block, color); 3]);
+            cells.push((b' ', white));
+        }
+        let (r, g, b) = to_rgb(self.stops[self.selected].1);
+        let value = [r, g, b][self.channel];
+        let label = format!("{} {value:3}", ["R", "G", "B"][self.channel]);
+        cells.extend(label.bytes().map(|c| (c, white)));
+
+        for (idx, (c, color)) in cells.into_iter().take(frame.width as usize).enumerate() {
+            frame.chars[idx] = c;
+            frame.colors[idx] = color;
+        }
+    }
+
+    /// One `threshold #RRGGBB` line per stop.
+    pub fn to_text(&self) -> String {
+        self.stops
+            .iter()
+            .map(|&(t, c)| format!("{t:.2} {}", to_hex(c)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Parses gradient stops in the format `PaletteEditor::to_text` prints: one
+/// `threshold #RRGGBB` per line, thresholds strictly rising from exactly 0 on
+/// the first stop to exactly 1 on the last. Blank lines and lines starting
+/// with `//` are skipped.
+pub fn parse_palette(text: &str) -> Result<Vec<(f32, Color)>, DonutError> {
+    let mut stops: Vec<(f32, Color)> = Vec::new();
+    let mut last_line = 0;
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+        let fail = |message: String| DonutError::PaletteParse { line: n + 1, message };
+        let (t, hex) = line
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| fail(format!("expected `threshold #RRGGBB`, got {line:?}")))?;
+        let t: f32 = t.parse().map_err(|_| fail(format!("invalid threshold: {t}")))?;
+        if !(0.0..=1.0).contains(&t) || stops.last().is_some_and(|&(prev, _)| t <= prev) {
+            return Err(fail(format!("threshold {t} not above the one before or outside 0..1")));
+        }
+        if stops.is_empty() && t != 0.0 {
+            return Err(fail(format!("the first threshold must be 0, got {t}")));
+        }
+        stops.push((t, parse_hex(hex.trim()).map_err(fail)?));
+        last_line = n + 1;
+    }
+    match stops.last() {
+        None => Err(DonutError::PaletteParse { line: 0, message: "no stops".into() }),
+        Some(&(t, _)) if t != 1.0 => Err(DonutError::PaletteParse {
+            line: last_line,
+            message: format!("the last threshold must be 1, got {t}"),
+        }),
+        Some(_) => Ok(stops),
+    }
+}
+
+/// Reads and parses a palette file.
+pub fn load_palette(path: &str) -> Result<Vec<(f32, Color)>, DonutError> {
+    parse_palette(&std::fs::read_to_string(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_error_line(text: &str) -> usize {
+        match parse_palette(text) {
+            Err(DonutError::PaletteParse { line, .. }) => line,
+            other => panic!("expected a PaletteParse error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_the_editor_format() {
+        let stops = parse_palette("// warm\n0.00 #000000\n\n0.50 #ff0000\n1.00 #ffffff\n").unwrap();
+        assert_eq!(stops.len(), 3);
+        assert_eq!(stops[1], (0.5, Color::Rgb { r: 255, g: 0, b: 0 }));
+    }
+
+    #[test]
+    fn malformed_file_reports_palette_parse() {
+        assert_eq!(parse_error_line("0.0 #000000\nnot a stop\n1.0 #ffffff"), 2);
+        assert_eq!(parse_error_line("0.0 #000000\n0.5 #zzzzzz\n1.0 #ffffff"), 2);
+        assert_eq!(parse_error_line("0.0 #000000\nhalf #ff0000\n1.0 #ffffff"), 2);
+        assert_eq!(parse_error_line(""), 0);
+    }
+
+    #[test]
+    fn thresholds_must_rise_strictly_from_0_to_1() {
+        // out of order, repeated, missing either end or outside 0..1
+        assert_eq!(parse_error_line("0.0 #000000\n0.6 #ff0000\n0.4 #00ff00\n1.0 #ffffff"), 3);
+        assert_eq!(parse_error_line("0.0 #000000\n0.5 #ff0000\n0.5 #00ff00\n1.0 #ffffff"), 3);
+        assert_eq!(parse_error_line("0.2 #000000\n1.0 #ffffff"), 1);
+        assert_eq!(parse_error_line("0.0 #000000\n// end\n0.8 #ffffff\n"), 3);
+        assert_eq!(parse_error_line("0.0 #000000\n1.5 #ffffff"), 2);
+    }
+
+    #[test]
+    fn number_keys_pick_a_stop_and_arrows_nudge_its_channel() {
+        let rgb = |r, g, b| Color::Rgb { r, g, b };
+        let mut editor = PaletteEditor::new(&[(0.0, rgb(0, 0, 0)), (1.0, rgb(250, 100, 4))]);
+        assert!(editor.apply(Action::SelectStop(1)));
+        // past the last stop: still an editing key, but nothing changes
+        assert!(editor.apply(Action::SelectStop(5)));
+        assert_eq!(editor.selected, 1);
+
+        // red saturates at 255, green steps down, left from red wraps to blue
+        editor.apply(Action::LightUp);
+        assert_eq!(editor.stops[1].1, rgb(255, 100, 4));
+        editor.apply(Action::LightRight);
+        editor.apply(Action::LightDown);
+        assert_eq!(editor.stops[1].1, rgb(255, 100 - CHANNEL_STEP, 4));
+        editor.apply(Action::LightRight);
+        editor.apply(Action::LightRight);
+        editor.apply(Action::LightLeft);
+        assert_eq!(editor.channel, 2);
+        editor.apply(Action::LightDown);
+        assert_eq!(editor.stops[1].1, rgb(255, 100 - CHANNEL_STEP, 0));
+        // the other stop is untouched, and other keys aren't the editor's
+        assert_eq!(editor.stops[0], (0.0, rgb(0, 0, 0)));
+        assert!(!editor.apply(Action::Quit));
+    }
+}
@@ -0,0 +1,53 @@
+use std::str::FromStr;
+
+use crossterm::style::Color;
+
+use crate::color::lerp_color;
+
+/// Procedural surface pattern driven by the torus (u, v) coordinates.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Pattern {
+    #[default]
+    Solid,
+    // diagonal candy-cane bands winding around the tube
+    Stripes,
+    Checker,
+}
+
+impl Pattern {
+    /// Whether (u, v) falls on the alternate cells of the pattern; `scale`
+    /// is the number of periods around each circle.
+    pub fn is_alternate(self, u: f32, v: f32, scale: f32) -> bool {
+        let cell = match self {
+            Pattern::Solid => return false,
+            Pattern::Stripes => ((u + v) * scale).floor() as i32,
+            Pattern::Checker => (u * scale).floor() as i32 + (v * scale).floor() as i32,
+        };
+        cell.rem_euclid(2) == 1
+    }
+
+    /// Applies the pattern to an already shaded color by washing the
+    /// alternate cells out toward white.
+    pub fn apply(self, color: Color, u: f32, v: f32, scale: f32) -> Color {
+        if self.is_alternate(u, v, scale) {
+            lerp_color(&color, &Color::Rgb { r: 255, g: 255, b: 255 }, 0.6)
+        } else {
+            color
+        }
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "solid" => Ok(Pattern::Solid),
+            "stripes" => Ok(Pattern::Stripes),
+            "checker" => Ok(Pattern::Checker),
+            _ => Err(format!("unknown pattern: {s}")),
+        }
+    }
+}
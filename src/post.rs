@@ -0,0 +1,473 @@
+use crossterm::style::Color;
+
+use crate::color::{ansi256_rgb, luminance, rgb_to_ansi256, to_rgb};
+use crate::math::Vec3;
+
+/// Marks hit cells that sit on a silhouette or crease.
+///
+/// `depth` holds the hit distance per cell (`f32::INFINITY` for misses) and
+/// `normals` the surface normal. A hit cell is an edge when a 4-neighbour
+/// missed, lies more than `depth_threshold` further away, or has a normal
+/// whose dot product with ours drops below `normal_threshold`. Only the
+/// nearer side of a depth step is marked so the outline hugs the object.
+pub fn detect_edges(
+    depth: &[f32],
+    normals: &[Vec3],
+    width: usize,
+    height: usize,
+    depth_threshold: f32,
+    normal_threshold: f32,
+) -> Vec<bool> {
+    let mut edges = vec![false; width * height];
+    for j in 0..height {
+        for i in 0..width {
+            let idx = i + j * width;
+            if !depth[idx].is_finite() {
+                continue;
+            }
+            let neighbours = [
+                (i > 0).then(|| idx - 1),
+                (i + 1 < width).then(|| idx + 1),
+                (j > 0).then(|| idx - width),
+                (j + 1 < height).then(|| idx + width),
+            ];
+            edges[idx] = neighbours.into_iter().flatten().any(|n| {
+                !depth[n].is_finite()
+                    || depth[n] - depth[idx] > depth_threshold
+                    || normals[n].dot(normals[idx]) < normal_threshold
+            });
+        }
+    }
+    edges
+}
+
+/// Box-blurred glow from bright cells onto the background.
+///
+/// Cells whose color has at least `threshold` luminance shine; every
+/// `background` cell within `radius` of one gets the average of the shining
+/// colors in its `(2 * radius + 1)²` window, so the glow fades with the share
+/// of bright cells around it. `None` where nothing shines nearby.
+pub fn bloom(
+    colors: &[Color],
+    background: &[bool],
+    width: usize,
+    height: usize,
+    threshold: f32,
+    radius: usize,
+) -> Vec<Option<Color>> {
+    let bright: Vec<Option<(u8, u8, u8)>> = colors
+        .iter()
+        .map(|&c| (luminance(c) >= threshold).then(|| to_rgb(c)))
+        .collect();
+    let area = ((2 * radius + 1) * (2 * radius + 1)) as u32;
+    let mut glow = vec![None; width * height];
+    for j in 0..height {
+        for i in 0..width {
+            let idx = i + j * width;
+            if !background[idx] {
+                continue;
+            }
+            let mut sum = [0u32; 3];
+            let mut any = false;
+            for y in j.saturating_sub(radius)..(j + radius + 1).min(height) {
+                for x in i.saturating_sub(radius)..(i + radius + 1).min(width) {
+                    if let Some((r, g, b)) = bright[x + y * width] {
+                        sum[0] += r as u32;
+                        sum[1] += g as u32;
+                        sum[2] += b as u32;
+                        any = true;
+                    }
+                }
+            }
+            if any {
+                let [r, g, b] = sum.map(|c| (c / area) as u8);
+                glow[idx] = Some(Color::Rgb { r, g, b });
+            }
+        }
+    }
+    glow
+}
+
+/// Flat-shading normals from screen-space derivatives of the hit points.
+///
+/// `points` holds each cell's hit position (None for misses), sampled every
+/// `step` cells. The normal is the cross product of the differences to the
+/// next hit along the row and the column, taking the previous one at the
+/// far edges, and is turned to face `eye`. None where a cell has no hit
+/// neighbour in one of the two directions.
+pub fn face_normals(
+    points: &[Option<Vec3>],
+    width: usize,
+    height: usize,
+    step: usize,
+    eye: Vec3,
+) -> Vec<Option<Vec3>> {
+    let step = step.max(1);
+    let at = |i: usize, j: usize| (i < width && j < height).then(|| points[i + j * width]).flatten();
+    let mut normals = vec![None; width * height];
+    for j in 0..height {
+        for i in 0..width {
+            let Some(p) = at(i, j) else { continue };
+            let dx = at(i + step, j)
+                .map(|q| q.sub(p))
+                .or_else(|| i.checked_sub(step).and_then(|x| at(x, j)).map(|q| p.sub(q)));
+            let dy = at(i, j + step)
+                .map(|q| q.sub(p))
+                .or_else(|| j.checked_sub(step).and_then(|y| at(i, y)).map(|q| p.sub(q)));
+            if let (Some(dx), Some(dy)) = (dx, dy) {
+                let n = dx.cross(dy).norm();
+                normals[i + j * width] = Some(if n.dot(p.sub(eye)) > 0.0 { n.mul(-1.0) } else { n });
+            }
+        }
+    }
+    normals
+}
+
+/// Anti-aliasing partners for silhouette cells.
+///
+/// For every missed cell with partial `coverage` next to a hit, the index of
+/// its nearest hit 4-neighbour along with that coverage, so the caller can
+/// blend in a dimmed copy of the neighbour. None elsewhere.
+pub fn silhouette_blend(
+    depth: &[f32],
+    coverage: &[f32],
+    width: usize,
+    height: usize,
+) -> Vec<Option<(usize, f32)>> {
+    let mut blends = vec![None; width * height];
+    for j in 0..height {
+        for i in 0..width {
+            let idx = i + j * width;
+            if depth[idx].is_finite() || coverage[idx] <= 0.0 {
+                continue;
+            }
+            let neighbours = [
+                (i > 0).then(|| idx - 1),
+                (i + 1 < width).then(|| idx + 1),
+                (j > 0).then(|| idx - width),
+                (j + 1 < height).then(|| idx + width),
+            ];
+            blends[idx] = neighbours
+                .into_iter()
+                .flatten()
+                .filter(|&n| depth[n].is_finite())
+                .min_by(|&a, &b| depth[a].total_cmp(&depth[b]))
+                .map(|n| (n, coverage[idx]));
+        }
+    }
+    blends
+}
+
+/// Cells either side of a silhouette: hits with a missed 4-neighbour and
+/// misses with a hit one, going by `depth` (`f32::INFINITY` for misses).
+/// These are the only cells edge-only anti-aliasing supersamples.
+pub fn silhouette_cells(depth: &[f32], width: usize, height: usize) -> Vec<bool> {
+    let mut cells = vec![false; width * height];
+    for j in 0..height {
+        for i in 0..width {
+            let idx = i + j * width;
+            let neighbours = [
+                (i > 0).then(|| idx - 1),
+                (i + 1 < width).then(|| idx + 1),
+                (j > 0).then(|| idx - width),
+                (j + 1 < height).then(|| idx + width),
+            ];
+            let hit = depth[idx].is_finite();
+            cells[idx] = neighbours.into_iter().flatten().any(|n| depth[n].is_finite() != hit);
+        }
+    }
+    cells
+}
+
+/// Running blend of frames for trails: every cell of `acc`, a glyph level
+/// and RGB, moves `alpha` of the way toward the new frame's. `alpha = 1`
+/// keeps only the newest frame; smaller values let older ones linger, each
+/// weighted by `(1 - alpha)` per frame since.
+pub fn accumulate(acc: &mut [(f32, [f32; 3])], levels: &[f32], colors: &[Color], alpha: f32) {
+    for ((level, rgb), (&new_level, &color)) in acc.iter_mut().zip(levels.iter().zip(colors)) {
+        let (r, g, b) = to_rgb(color);
+        *level += (new_level - *level) * alpha;
+        for (c, new) in rgb.iter_mut().zip([r, g, b]) {
+            *c += (new as f32 - *c) * alpha;
+        }
+    }
+}
+
+/// Blur weight in `[0, 1]` for a hit at `depth` with the lens focused at
+/// `focus`: the thin-lens circle of confusion, `aperture * |depth - focus| /
+/// depth`, saturating at 1. Zero on the focus plane.
+pub fn circle_of_confusion(depth: f32, focus: f32, aperture: f32) -> f32 {
+    if !depth.is_finite() || depth <= 0.0 {
+        return 0.0;
+    }
+    (aperture * (depth - focus).abs() / depth).min(1.0)
+}
+
+/// Screen-space depth of field over glyph `levels` and colors.
+///
+/// Every hit cell with a nonzero circle of confusion is mixed with the
+/// average of its 3x3 neighbourhood by that amount; missed neighbours count
+/// as empty black cells, so out-of-focus silhouettes soften. None where a
+/// cell stays as it is.
+pub fn depth_of_field(
+    levels: &[f32],
+    colors: &[Color],
+    depth: &[f32],
+    width: usize,
+    height: usize,
+    focus: f32,
+    aperture: f32,
+) -> Vec<Option<(f32, Color)>> {
+    let mut blurred = vec![None; width * height];
+    for j in 0..height {
+        for i in 0..width {
+            let idx = i + j * width;
+            let coc = circle_of_confusion(depth[idx], focus, aperture);
+            if coc <= 0.0 {
+                continue;
+            }
+            let mut level = 0.0;
+            let mut sum = [0.0f32; 3];
+            let mut count = 0.0;
+            for y in j.saturating_sub(1)..(j + 2).min(height) {
+                for x in i.saturating_sub(1)..(i + 2).min(width) {
+                    let n = x + y * width;
+                    if depth[n].is_finite() {
+                        let (r, g, b) = to_rgb(colors[n]);
+                        level += levels[n];
+                        sum[0] += r as f32;
+                        sum[1] += g as f32;
+                        sum[2] += b as f32;
+                    }
+                    count += 1.0;
+                }
+            }
+            let (r, g, b) = to_rgb(colors[idx]);
+            let mix = |own: f32, total: f32| own + (total / count - own) * coc;
+            let [r, g, b] = [(r, sum[0]), (g, sum[1]), (b, sum[2])]
+                .map(|(own, total)| mix(own as f32, total).round() as u8);
+            blurred[idx] = Some((mix(levels[idx], level), Color::Rgb { r, g, b }));
+        }
+    }
+    blurred
+}
+
+/// Floyd–Steinberg dithering of `Rgb` cells down to the 256-color palette.
+///
+/// Each cell's quantization error is passed on per channel to its right
+/// and lower neighbours (7/16, 3/16, 5/16, 1/16), so runs of cells average
+/// out to the true color instead of banding. Other colors (the background)
+/// are kept as they are and neither take nor pass on error. Inherently
+/// serial, row by row.
+pub fn dither_ansi256(colors: &[Color], width: usize, height: usize) -> Vec<Color> {
+    let mut error = vec![[0.0f32; 3]; width * height];
+    let mut out = colors.to_vec();
+    for j in 0..height {
+        for i in 0..width {
+            let idx = i + j * width;
+            let Color::Rgb { r, g, b } = colors[idx] else { continue };
+            let want = [r, g, b].map(|c| c as f32);
+            let target = [0, 1, 2].map(|c| (want[c] + error[idx][c]).clamp(0.0, 255.0));
+            let [r, g, b] = target.map(|c| c.round() as u8);
+            let index = rgb_to_ansi256(r, g, b);
+            out[idx] = Color::AnsiValue(index);
+
+            let (pr, pg, pb) = ansi256_rgb(index);
+            let residual = [pr, pg, pb].map(|c| c as f32);
+            let residual = [0, 1, 2].map(|c| target[c] - residual[c]);
+            let mut spread = |n: Option<usize>, weight: f32| {
+                if let Some(n) = n.filter(|&n| matches!(colors[n], Color::Rgb { .. })) {
+                    for c in 0..3 {
+                        error[n][c] += residual[c] * weight;
+                    }
+                }
+            };
+            let below = j + 1 < height;
+            spread((i + 1 < width).then(|| idx + 1), 7.0 / 16.0);
+            spread((below && i > 0).then(|| idx + width - 1), 3.0 / 16.0);
+            spread(below.then(|| idx + width), 5.0 / 16.0);
+            spread((below && i + 1 < width).then(|| idx + width + 1), 1.0 / 16.0);
+        }
+    }
+    out
+}
+
+/// Scatters every cell that has surface coordinates in `uvs` to where its
+/// (u, v) falls on the `width` x `height` grid, u across and v down, to show
+/// how the parameterization wraps the surface. Cells nothing lands on stay
+/// blank; where several land on one, the last wins.
+pub fn unwrap_uv(
+    chars: &[u8],
+    colors: &[Color],
+    uvs: &[Option<(f32, f32)>],
+    width: usize,
+    height: usize,
+) -> (Vec<u8>, Vec<Color>) {
+    let (mut out_chars, mut out_colors) = (vec![b' '; width * height], vec![Color::Reset; width * height]);
+    for (idx, &(u, v)) in uvs.iter().enumerate().filter_map(|(idx, uv)| Some((idx, uv.as_ref()?))) {
+        let x = ((u * width as f32) as usize).min(width - 1);
+        let y = ((v * height as f32) as usize).min(height - 1);
+        out_chars[x + y * width] = chars[idx];
+        out_colors[x + y * width] = colors[idx];
+    }
+    (out_chars, out_colors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::{orthonormal_basis, Vec2};
+    use crate::sdf::torus_uv;
+
+    #[test]
+    fn depth_step_is_an_edge_and_a_flat_region_is_not() {
+        // 6x3: the left half twice as near as the right
+        let (w, h) = (6, 3);
+        let depth: Vec<f32> = (0..w * h).map(|k| if k % w < 3 { 1.0 } else { 2.0 }).collect();
+        let normals = vec![Vec3::new(0.0, 0.0, -1.0); w * h];
+        let edges = detect_edges(&depth, &normals, w, h, 0.5, 0.8);
+        for j in 0..h {
+            let row: Vec<bool> = edges[j * w..(j + 1) * w].to_vec();
+            assert_eq!(row, [false, false, true, false, false, false]);
+        }
+        let flat = vec![1.0; w * h];
+        assert!(detect_edges(&flat, &normals, w, h, 0.5, 0.8).iter().all(|&e| !e));
+    }
+
+    #[test]
+    fn bright_cell_bleeds_a_dimmer_glow_into_its_neighbours() {
+        // 5x5 dark background around one white cell in the middle
+        let (w, h) = (5, 5);
+        let mut colors = vec![Color::Rgb { r: 0, g: 0, b: 0 }; w * h];
+        colors[12] = Color::Rgb { r: 255, g: 255, b: 255 };
+        let background: Vec<bool> = (0..w * h).map(|k| k != 12).collect();
+        let glow = bloom(&colors, &background, w, h, 0.9, 1);
+        for j in 0..h {
+            for i in 0..w {
+                let near = i.abs_diff(2) <= 1 && j.abs_diff(2) <= 1 && (i, j) != (2, 2);
+                match glow[i + j * w] {
+                    Some(c) if near => assert_eq!(to_rgb(c), (28, 28, 28)),
+                    None if !near => {}
+                    other => panic!("({i}, {j}) glows {other:?}"),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn planar_hit_points_give_one_normal_facing_the_eye() {
+        // hits on the tilted plane z = 0.5 x + 0.25 y, seen from above
+        let (w, h) = (6, 4);
+        let points: Vec<Option<Vec3>> = (0..w * h)
+            .map(|k| {
+                let (x, y) = ((k % w) as f32, (k / w) as f32);
+                Some(Vec3::new(x, y, 0.5 * x + 0.25 * y))
+            })
+            .collect();
+        let expected = Vec3::new(-0.5, -0.25, 1.0).norm();
+        let eye = Vec3::new(2.0, 2.0, 10.0);
+        for step in [1, 2] {
+            for n in face_normals(&points, w, h, step, eye) {
+                let n = n.expect("every cell has neighbours on the plane");
+                assert!(n.sub(expected).len() < 1e-5, "{n:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn dithered_rows_average_out_to_the_true_color() {
+        // between cube levels in every channel: plain nearest is 40 off in green
+        let (want, (w, h)) = ([115.0, 40.0, 200.0], (64, 8));
+        let c = Color::Rgb { r: 115, g: 40, b: 200 };
+        let out = dither_ansi256(&vec![c; w * h], w, h);
+        let mut sum = [0.0f32; 3];
+        for &cell in &out {
+            let Color::AnsiValue(index) = cell else { panic!("{cell:?} is not a palette color") };
+            let (r, g, b) = ansi256_rgb(index);
+            for (s, (got, want)) in sum.iter_mut().zip([r, g, b].into_iter().zip(want)) {
+                *s += got as f32 - want;
+            }
+        }
+        // what's left is the error dropped off the last row and column
+        for s in sum {
+            assert!((s / (w * h) as f32).abs() < 1.0, "{sum:?}");
+        }
+        let (_, g, _) = ansi256_rgb(rgb_to_ansi256(115, 40, 200));
+        assert!((g as f32 - 40.0).abs() >= 40.0);
+    }
+
+    #[test]
+    fn focus_plane_is_sharp_and_the_distance_is_blurred() {
+        assert_eq!(circle_of_confusion(3.0, 3.0, 2.0), 0.0);
+        assert!(circle_of_confusion(3.3, 3.0, 2.0) < circle_of_confusion(4.0, 3.0, 2.0));
+        assert_eq!(circle_of_confusion(100.0, 3.0, 2.0), 1.0);
+        assert_eq!(circle_of_confusion(f32::INFINITY, 3.0, 2.0), 0.0);
+
+        // a 3x3 block: the middle in focus, the rest far behind it
+        let mut depth = vec![50.0; 9];
+        depth[4] = 3.0;
+        let colors: Vec<Color> = (0..9).map(|k| Color::Rgb { r: 20 * k as u8, g: 0, b: 0 }).collect();
+        let blurred = depth_of_field(&[0.5; 9], &colors, &depth, 3, 3, 3.0, 2.0);
+        assert_eq!(blurred[4], None);
+        // saturated: exactly the neighbourhood average
+        let (_, corner) = blurred[0].unwrap();
+        assert_eq!(to_rgb(corner), ((20 + 60 + 80) / 4, 0, 0));
+    }
+
+    #[test]
+    fn full_alpha_keeps_the_newest_frame_and_less_keeps_a_trail() {
+        let (old, new) = (Color::Rgb { r: 200, g: 100, b: 0 }, Color::Rgb { r: 0, g: 100, b: 200 });
+        let start = |level: f32, color: Color| {
+            let (r, g, b) = to_rgb(color);
+            vec![(level, [r as f32, g as f32, b as f32])]
+        };
+        let mut acc = start(1.0, old);
+        accumulate(&mut acc, &[0.0], &[new], 1.0);
+        assert_eq!(acc, start(0.0, new));
+
+        // two frames at 0.5: the old one still weighs a quarter
+        let mut acc = start(1.0, old);
+        accumulate(&mut acc, &[0.0], &[new], 0.5);
+        assert_eq!(acc, vec![(0.5, [100.0, 100.0, 100.0])]);
+        accumulate(&mut acc, &[0.0], &[new], 0.5);
+        assert_eq!(acc, vec![(0.25, [50.0, 100.0, 150.0])]);
+    }
+
+    #[test]
+    fn major_circle_unwraps_to_a_rising_u() {
+        let (t, axis) = (Vec2::new(1.0, 0.3), Vec3::new(1.0, 1.0, 1.0).norm());
+        let (a, b) = orthonormal_basis(axis);
+        // around the outer equator, starting where u is 0
+        let n = 16;
+        let uvs: Vec<Option<(f32, f32)>> = (0..n)
+            .map(|k| {
+                let angle = k as f32 / n as f32 * std::f32::consts::TAU;
+                let p = a.mul(angle.cos()).add(b.mul(angle.sin())).mul(t.x + t.y);
+                Some(torus_uv(p, t, axis))
+            })
+            .collect();
+        let us: Vec<f32> = uvs.iter().map(|uv| uv.unwrap().0).collect();
+        assert!(us.windows(2).all(|w| w[1] > w[0]), "{us:?}");
+
+        // on an unwrapped strip they land left to right, one cell each
+        let chars: Vec<u8> = (b'a'..b'a' + n as u8).collect();
+        let (out, _) = unwrap_uv(&chars, &[Color::Reset; 16], &uvs, n, 1);
+        assert_eq!(out, chars);
+    }
+
+    #[test]
+    fn only_cells_beside_a_silhouette_are_supersampled() {
+        // a 3x3 hit block in the middle of a 7x5 miss field
+        let (w, h) = (7, 5);
+        let mask = ["ooooooo", "o###ooo", "o###ooo", "o###ooo", "ooooooo"];
+        let depth: Vec<f32> =
+            mask.concat().bytes().map(|c| if c == b'#' { 1.0 } else { f32::INFINITY }).collect();
+        let picked: Vec<String> = silhouette_cells(&depth, w, h)
+            .chunks(w)
+            .map(|row| row.iter().map(|&s| if s { 'x' } else { '.' }).collect())
+            .collect();
+        // the block's middle and the misses further out are left alone
+        assert_eq!(picked, [".xxx...", "xxxxx..", "xx.xx..", "xxxxx..", ".xxx..."]);
+        assert!(silhouette_cells(&[1.0; 6], 3, 2).iter().all(|&s| !s));
+    }
+}
@@ -0,0 +1,1302 @@
+use std::f32::consts::PI;
+use std::time::{Duration, Instant};
+
+use crossterm::style::Color;
+
+use crate::camera::{Camera, Shake};
+use crate::color::{
+    add_light, adjust_intensity, fog_factor, lerp_color, luminance, normal_color, rotate_hue, sample_gradient,
+    sample_gradient_with, to_rgb, Lut, GRADIENT,
+};
+use crate::config::Config;
+use crate::floor::Floor;
+use crate::font::{text_cells, text_width, GLYPH_HEIGHT};
+use crate::light::{apply_tint, highlights, illuminate, orbit_light, Light};
+use crate::march::{coverage, march, march_with, soft_shadow, MarchResult, RaymarchConfig};
+use crate::math::{mix, Mat3, Vec2, Vec3};
+use crate::post::{
+    accumulate, bloom, depth_of_field, detect_edges, face_normals, silhouette_blend, silhouette_cells,
+    unwrap_uv,
+};
+use crate::rng::{cell_jitter, halton};
+use crate::scene::Scene;
+use crate::screensaver::Screensaver;
+use crate::sdf::{
+    estimate_hit_normal, estimate_normal, op_displace, op_intersect, op_morph, op_scale, sd_plane, sd_sphere,
+};
+use crate::starfield::Starfield;
+use crate::stats::StageTimes;
+
+const COLOR_SCALE: f32 = 1.5; // Adjusted scaling factor for color intensity
+const GLYPHS: &[u8] = b" .:-=+*#%@"; // Brighter character progression
+
+// drawn where rays miss through the hole with --hole-color
+const HOLE_GLYPH: u8 = b'.';
+
+// --taa blends each frame this far into the history and cycles through this
+// many sub-cell ray offsets
+const TAA_BLEND: f32 = 0.25;
+const TAA_OFFSETS: u32 = 8;
+
+/// Per-cell output of one rendered frame.
+#[derive(Clone, Debug)]
+pub struct Frame {
+    pub width: u16,
+    pub height: u16,
+    pub chars: Vec<u8>,
+    pub colors: Vec<Color>,
+    // hit distance along the ray, infinite where it missed
+    pub depth: Vec<f32>,
+    pub normals: Vec<Vec3>,
+    // march steps taken by each cell's ray
+    pub steps: Vec<u32>,
+    // estimated share of the cell covered by geometry: 1 for hits, partial
+    // for misses that passed within a cell of a surface
+    pub coverage: Vec<f32>,
+}
+
+impl Frame {
+    pub fn new(width: u16, height: u16) -> Self {
+        let n = (width as usize) * (height as usize);
+        Self {
+            width,
+            height,
+            chars: vec![b' '; n],
+            colors: vec![Color::Reset; n],
+            depth: vec![f32::INFINITY; n],
+            normals: vec![Vec3::default(); n],
+            steps: vec![0; n],
+            coverage: vec![0.0; n],
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.chars.fill(b' ');
+        self.colors.fill(Color::Reset);
+        self.depth.fill(f32::INFINITY);
+        self.normals.fill(Vec3::default());
+        self.steps.fill(0);
+        self.coverage.fill(0.0);
+    }
+
+    /// Splits the buffers into bands of `rows` full rows each.
+    fn bands(&mut self, rows: usize) -> impl Iterator<Item = Rows<'_>> {
+        let n = (rows * self.width as usize).max(1);
+        self.chars
+            .chunks_mut(n)
+            .zip(self.colors.chunks_mut(n))
+            .zip(self.depth.chunks_mut(n))
+            .zip(self.normals.chunks_mut(n))
+            .zip(self.steps.chunks_mut(n))
+            .zip(self.coverage.chunks_mut(n))
+            .map(|(((((chars, colors), depth), normals), steps), coverage)| Rows {
+                chars,
+                colors,
+                depth,
+                normals,
+                steps,
+                coverage,
+            })
+    }
+
+    /// Copies the top-left cell of every `n`x`n` block over the rest of the
+    /// block; step counts stay with the cells that were actually marched.
+    pub fn replicate_blocks(&mut self, n: usize) {
+        if n <= 1 {
+            return;
+        }
+        let (w, h) = (self.width as usize, self.height as usize);
+        for j in 0..h {
+            for i in 0..w {
+                let (idx, src) = (i + j * w, (i - i % n) + (j - j % n) * w);
+                if idx != src {
+                    self.chars[idx] = self.chars[src];
+                    self.colors[idx] = self.colors[src];
+                    self.depth[idx] = self.depth[src];
+                    self.normals[idx] = self.normals[src];
+                    self.coverage[idx] = self.coverage[src];
+                }
+            }
+        }
+    }
+
+    /// Writes `text` from cell (`x`, `y`) rightwards in `color`, cut off at
+    /// the right edge.
+    pub fn put_text(&mut self, x: u16, y: u16, text: &str, color: Color) {
+        if y >= self.height {
+            return;
+        }
+        let row = y as usize * self.width as usize;
+        for (i, c) in (x..self.width).zip(text.bytes()) {
+            self.chars[row + i as usize] = c;
+            self.colors[row + i as usize] = color;
+        }
+    }
+
+    /// The glyphs only, one line per row.
+    pub fn to_text(&self) -> String {
+        self.chars
+            .chunks(self.width as usize)
+            .map(|row| row.iter().map(|&c| c as char).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// What the shade pass shows of every cell.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum RenderMode {
+    #[default]
+    Shaded,
+    // hits colored by their surface normal
+    Normals,
+    // glyph and gray level by how near the hit is
+    Depth,
+    // heatmap of the march steps each ray took
+    Steps,
+    // shaded, with silhouettes and creases drawn over it
+    Outline,
+}
+
+impl RenderMode {
+    pub const ALL: [RenderMode; 5] =
+        [RenderMode::Shaded, RenderMode::Normals, RenderMode::Depth, RenderMode::Steps, RenderMode::Outline];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RenderMode::Shaded => "shaded",
+            RenderMode::Normals => "normals",
+            RenderMode::Depth => "depth",
+            RenderMode::Steps => "steps",
+            RenderMode::Outline => "outline",
+        }
+    }
+
+    /// The mode after this one, wrapping from the last back to the first.
+    pub fn next(self) -> Self {
+        let k = Self::ALL.iter().position(|&m| m == self).unwrap_or(0);
+        Self::ALL[(k + 1) % Self::ALL.len()]
+    }
+}
+
+/// Where the primary ray through a cell ends up on the scene.
+#[derive(Copy, Clone, Debug)]
+struct Surface {
+    // distance along the ray
+    depth: f32,
+    point: Vec3,
+    normal: Vec3,
+    // index into the scene's objects
+    object: usize,
+}
+
+/// G-buffer entry for one marched cell: everything the geometry pass finds
+/// that shading needs, so neighbour-based passes can run in between.
+#[derive(Copy, Clone, Debug)]
+struct Geometry {
+    rd: Vec3,
+    // distance to the floor along the ray, if it crosses it
+    floor_k: Option<f32>,
+    march: MarchResult,
+    surface: Option<Surface>,
+}
+
+/// Blends `frame` into the running glyph levels and colors of `history` by
+/// `alpha`, then shows the blend in `frame`.
+fn blend_history(history: &mut Vec<(f32, [f32; 3])>, frame: &mut Frame, alpha: f32) {
+    let levels: Vec<f32> =
+        frame.chars.iter().map(|&c| GLYPHS.iter().position(|&g| g == c).unwrap_or(0) as f32).collect();
+    if history.len() != levels.len() {
+        // nothing to blend with on the first frame or after a resize
+        *history = vec![(0.0, [0.0; 3]); levels.len()];
+        accumulate(history, &levels, &frame.colors, 1.0);
+    } else {
+        accumulate(history, &levels, &frame.colors, alpha);
+    }
+    for (idx, &(level, [r, g, b])) in history.iter().enumerate() {
+        frame.chars[idx] = GLYPHS[(level.round() as usize).min(GLYPHS.len() - 1)];
+        if frame.chars[idx] != b' ' {
+            frame.colors[idx] = Color::Rgb { r: r as u8, g: g as u8, b: b as u8 };
+        }
+    }
+}
+
+/// Triangle wave over frame `t`: counts up from 0 to `period`, back down to
+/// 0 and so on, so a clip of `2 * period` frames loops without a jump.
+pub fn ping_pong(t: u32, period: u32) -> u32 {
+    if period == 0 {
+        return 0;
+    }
+    let phase = t % (2 * period);
+    if phase <= period { phase } else { 2 * period - phase }
+}
+
+/// Mutable view of a run of whole rows of a `Frame`.
+struct Rows<'a> {
+    chars: &'a mut [u8],
+    colors: &'a mut [Color],
+    depth: &'a mut [f32],
+    normals: &'a mut [Vec3],
+    steps: &'a mut [u32],
+    coverage: &'a mut [f32],
+}
+
+/// Renders frames of the animation; `render(t)` shows exactly what the
+/// animation loop shows at frame `t`.
+pub struct Renderer {
+    cfg: Config,
+    // scene parameters
+    pub lights: Vec<Light>,                      // the first is the one the arrow keys steer
+    pub camera: Camera,                          // orbits the origin from (-2.5,0,0)
+    pub pixel_aspect: f32,                       // non-square terminal pixels
+    pub pulse_amp: f32,                          // relative size change
+    pub pulse_speed: f32,                        // radians per frame
+    pub ripple_freq: f32,                        // base spatial frequency
+    pub ripple_speed: f32,                       // frequency sweep rate
+    pub edge_depth: f32,                         // depth step (in march steps) that counts as an edge
+    pub edge_normal: f32,                        // cos of the crease angle
+    pub floor: Option<Floor>,                    // checkerboard below the scene
+    pub march: RaymarchConfig,
+    pub subsample: usize,                        // march every nth cell per axis
+    pub palette: Option<Vec<(f32, Color)>>,      // gradient stops replacing the theme's
+    pub stage_times: StageTimes,                 // how long the last render spent in each stage
+    pub mode: RenderMode,                        // what the shade pass shows
+    pub march_logged: bool,                      // whether --march-log has printed its ray yet
+    #[cfg(feature = "threads")]
+    pub threads: usize,                          // row bands rendered in parallel
+    // rotation speed multiplier, and the (frame, angle) it applies from so
+    // speed changes don't make the donut jump
+    speed: f32,
+    spin_base: (u32, f32),
+    scene: Scene,
+    stars: Starfield,
+    saver: Option<Screensaver>,
+    // last frame the screensaver was advanced to; it is stateful and only
+    // steps forward
+    saver_frame: Option<u32>,
+    // blended glyph level and color per cell for --accumulate
+    accum: Vec<(f32, [f32; 3])>,
+    // the same for --taa
+    history: Vec<(f32, [f32; 3])>,
+}
+
+impl Renderer {
+    pub fn new(cfg: &Config) -> Self {
+        let in_rad = 0.3_f32;                    // tube radius
+        let out_rad = 1.2_f32;                   // main radius
+        let torus = Vec2::new(out_rad, in_rad);
+        let base_axis = Vec3::new(1.0, 1.0, 1.0).norm();
+        let scene = match (cfg.chain, cfg.knot) {
+            (Some(n), _) => Scene::chain(n),
+            (None, Some(knot)) => Scene::knot(knot, base_axis),
+            (None, None) if cfg.twin => Scene::twin(torus, base_axis),
+            (None, None) => Scene::donut(torus, base_axis),
+        };
+        let scene = match &cfg.text3d {
+            Some(text) if cfg.text3d_union => scene.union(Scene::text(text)),
+            Some(text) => Scene::text(text),
+            None => scene,
+        };
+        let mut renderer = Self {
+            cfg: cfg.clone(),
+            lights: match cfg.lighting {
+                Some(preset) => preset.lights(&Camera::default()),
+                None => vec![Light::white(Vec3::new(-1.0, -1.0, -1.0))],
+            },
+            camera: Camera::default(),
+            pixel_aspect: 11.0 / 24.0,
+            pulse_amp: 0.1,
+            pulse_speed: 2.0 * (PI / 180.0),
+            ripple_freq: 8.0,
+            ripple_speed: 1.0 * (PI / 180.0),
+            edge_depth: 2.5,
+            edge_normal: 0.5,
+            subsample: cfg.subsample,
+            palette: cfg.palette.clone(),
+            stage_times: StageTimes::default(),
+            mode: if cfg.normals { RenderMode::Normals } else { RenderMode::Shaded },
+            march: RaymarchConfig {
+                min_step: cfg.min_step,
+                base_eps: cfg.base_eps,
+                ..RaymarchConfig::default()
+            },
+            // just below the pulsing donut's lowest point
+            floor: cfg.floor.map(|square| Floor { height: 1.7, square, fog: 0.15 }),
+            #[cfg(feature = "threads")]
+            threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            speed: 1.0,
+            spin_base: (0, 0.0),
+            scene,
+            stars: Starfield { density: cfg.stars, seed: cfg.seed(), twinkle: cfg.twinkle },
+            saver: None,
+            saver_frame: None,
+            accum: Vec::new(),
+            history: Vec::new(),
+            march_logged: false,
+        };
+        // framed for the donut at the top of its pulse
+        if let Some(fill) = cfg.fit {
+            let radius = renderer.scene.bounding_radius() * (1.0 + renderer.pulse_amp);
+            renderer.camera.fit(radius, fill);
+        }
+        renderer
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Changes the rotation speed from frame `t` on, continuing from the
+    /// angle the donut has at `t`.
+    pub fn set_speed(&mut self, t: u32, speed: f32) {
+        let t = self.animation_time(t);
+        self.spin_base = (t, self.spin_angle(t));
+        self.speed = speed;
+    }
+
+    /// Radii of the donut, None when the scene isn't the single donut.
+    pub fn radii(&self) -> Option<Vec2> {
+        self.scene.donut_radii()
+    }
+
+    /// Reshapes the single donut; the march step and far plane follow.
+    pub fn set_radii(&mut self, radii: Vec2) {
+        self.scene.set_donut_radii(radii);
+    }
+
+    /// Frame `t` of the animation timeline, which with `--ping-pong` runs
+    /// back and forth instead of only forward.
+    fn animation_time(&self, t: u32) -> u32 {
+        self.cfg.ping_pong.map_or(t, |period| ping_pong(t, period))
+    }
+
+    /// The camera for frame `t`, jolted by `--shake`.
+    fn shaken_camera(&self, t: u32) -> Camera {
+        let cfg = &self.cfg;
+        match cfg.shake {
+            Some(amplitude) => {
+                let shake = Shake { amplitude, frequency: cfg.shake_frequency, duration: cfg.shake_duration };
+                shake.apply(self.camera, t, cfg.seed())
+            }
+            None => self.camera,
+        }
+    }
+
+    /// The lights in world space at frame `t` for a camera with `basis`.
+    ///
+    /// Orbiting lights circle the scene on their own, whatever the camera and
+    /// shape do; headlamp lights are given in camera space and orbit with it.
+    fn world_lights(&self, t: u32, basis: Mat3) -> Vec<Light> {
+        let cfg = &self.cfg;
+        let orbit = cfg.light_orbit.map_or(0.0, |speed| (t as f32) * speed * (PI / 180.0));
+        self.lights
+            .iter()
+            .map(|l| {
+                let dir = if orbit != 0.0 { orbit_light(l.dir, cfg.light_orbit_axis, orbit) } else { l.dir };
+                Light { dir: if cfg.headlamp { basis * dir } else { dir }, ..*l }
+            })
+            .collect()
+    }
+
+    // original used "degrees = t", convert to radians; slow it down a bit
+    fn spin_angle(&self, t: u32) -> f32 {
+        let (t0, a0) = self.spin_base;
+        a0 + (t as f32 - t0 as f32) * self.speed * 0.6_f32 * (PI / 180.0)
+    }
+
+    /// Steps the screensaver to frame `t`, restarting it from its seed when
+    /// asked to go backwards.
+    fn advance_saver(&mut self, t: u32) {
+        if !self.cfg.screensaver {
+            return;
+        }
+        if self.saver_frame.is_none_or(|f| f > t) {
+            self.saver = Some(Screensaver::new(self.cfg.seed()));
+            self.saver_frame = None;
+        }
+        let saver = self.saver.as_mut().unwrap();
+        let start = self.saver_frame.map_or(0, |f| f + 1);
+        for f in start..=t {
+            saver.advance(f);
+        }
+        self.saver_frame = Some(t);
+        self.scene = saver.scene();
+    }
+
+    pub fn render(&mut self, t: u32, frame: &mut Frame) {
+        // nothing to trace in a frame without cells
+        if frame.width == 0 || frame.height == 0 {
+            return;
+        }
+        let started = Instant::now();
+        // the shake fades over real frames, whatever the timeline does
+        let camera = self.shaken_camera(t);
+        let t = self.animation_time(t);
+        self.advance_saver(t);
+        // rotate the scene over time around X (what rot_z spins); for the
+        // donut this turns the (1,1,1) axis exactly like the original tdir
+        let (spin_axis, angle) = match &self.saver {
+            Some(saver) => saver.rotation(),
+            None => (Vec3::new(1.0, 0.0, 0.0), self.spin_angle(t)),
+        };
+        self.scene.animate(angle);
+        let cfg = &self.cfg;
+        let scene = &self.scene;
+        let mode = self.mode;
+        let (width, height) = (frame.width, frame.height);
+
+        // aspect and shading
+        let aspect = width as f32 / height as f32;
+        let grad_size = (GLYPHS.len() as i32) - 1;
+        let min_col = 1.0 / grad_size as f32;
+
+        // color cycling rotates the hue of every gradient stop over time
+        let hue = (t as f32 * cfg.color_cycle).rem_euclid(360.0);
+        let stops = match &self.palette {
+            Some(stops) => stops.iter().map(|&(t, c)| (t, rotate_hue(c, hue))).collect(),
+            None => cfg.theme.hue_rotated(hue),
+        };
+        // sampled once per frame, looked up per cell
+        let palette = Lut::new(|intensity| match &self.saver {
+            Some(saver) => rotate_hue(saver.color(intensity), hue),
+            None => sample_gradient_with(&stops, intensity, cfg.interpolation),
+        });
+        // breathe the whole scene in and out
+        let scale = 1.0 + self.pulse_amp * ((t as f32) * self.pulse_speed).sin();
+        // world -> object space is the inverse of the scene's spin
+        let unspin = Mat3::from_axis_angle(spin_axis, angle).transpose();
+        let (to_object_rot, view_rot) = cfg.spin.rotations(unspin);
+        let to_object = |q: Vec3| to_object_rot * q;
+        // sweeping the frequency makes the ripples travel across the surface
+        let freq = self.ripple_freq * (1.0 + 0.25 * ((t as f32) * self.ripple_speed).sin());
+        // blend weight toward the scene; 1 without morphing
+        let morph = cfg
+            .morph
+            .map_or(1.0, |speed| 0.5 + 0.5 * ((t as f32) * speed * (PI / 180.0)).cos());
+        let body = |q: Vec3| {
+            op_scale(q, scale, |q| {
+                op_displace(
+                    to_object(q),
+                    |q| op_morph(q, |q| sd_sphere(q, scene.extent), |q| scene.distance(q), morph),
+                    cfg.ripple,
+                    freq,
+                )
+            })
+        };
+        let tube = scene.step * scale;
+        // X forward by default (like original: rd = normalize(1, uv.x, uv.y))
+        let ro = view_rot * camera.position();
+        let basis = view_rot * camera.basis();
+        // the cross-section plane faces the camera and sweeps back and forth
+        // through the scene, cutting away the near side
+        let clip = cfg.clip.map(|speed| {
+            let sweep = ((t as f32) * speed * (PI / 180.0)).sin();
+            (basis.col(0).mul(-1.0), sweep * scene.extent * scale)
+        });
+        let sdf = |q: Vec3| match clip {
+            Some((n, offset)) => op_intersect(body(q), sd_plane(q, n, offset)),
+            None => body(q),
+        };
+        // like the C++ bound 2 * out_rad - camp_pos_x, with camp_pos_x
+        // half a unit in front of the camera
+        let far = scene.extent * scale * 2.0 + camera.distance - 0.5;
+        let lights = self.world_lights(t, basis);
+
+        frame.clear();
+
+        let (w, h) = (width as usize, height as usize);
+        // with subsampling only the top-left cell of each block is marched
+        let block = self.subsample.max(1);
+        let marched = |i: usize, j: usize| i.is_multiple_of(block) && j.is_multiple_of(block);
+
+        // the ray through cell (x, y), fractions for points between cell
+        // centers, the floor's distance along it and where it hits the scene
+        // in front of the floor
+        let ray = |x: f32, y: f32| {
+            // uv in [-1, 1], correct aspect and pixel aspect
+            let mut ux = (x / width as f32) * 2.0 - 1.0;
+            let uy = (y / height as f32) * 2.0 - 1.0;
+            ux *= aspect * self.pixel_aspect;
+
+            // ray dir through the camera basis
+            let rd = (basis * Vec3::new(1.0, ux, uy)).norm();
+
+            // the scene is only marched up to the floor, which hides the rest
+            let floor_k = self.floor.and_then(|floor| floor.intersect(ro, rd));
+            (rd, floor_k, floor_k.map_or(far, |fk| fk.min(far)))
+        };
+        let trace = |x: f32, y: f32| {
+            let (rd, floor_k, far) = ray(x, y);
+            // march along rd up to a rough far bound
+            (rd, floor_k, march(ro, rd, far, tube, &self.march, sdf))
+        };
+
+        // the steps of one ray through the middle row, for the first frame
+        if let Some(column) = cfg.march_log.filter(|_| !self.march_logged) {
+            let (i, j) = (column.min(width.saturating_sub(1)), height / 2);
+            let (rd, _, far) = ray(i as f32, j as f32);
+            eprintln!("march steps of cell ({i}, {j}): k\tx\ty\tz\tdistance\tstep");
+            let result = march_with(ro, rd, far, tube, &self.march, sdf, |s| eprintln!("{}", s.to_text()));
+            match result.depth {
+                Some(k) => eprintln!("hit at {k:.4} after {} steps", result.steps),
+                None => eprintln!("missed after {} steps", result.steps),
+            }
+        }
+
+        // diffuse light on the floor at `p`, `k` along the ray: dark squares
+        // at half brightness, both fading into the fog. With shadows each
+        // light is dimmed by whatever the scene blocks on the way to it
+        let floor_light = |floor: Floor, p: Vec3, k: f32| {
+            let check = if floor.is_light(p) { 1.0 } else { 0.5 };
+            let lit = match cfg.shadows {
+                Some(softness) => {
+                    let start = p.add(Floor::NORMAL.mul(tube));
+                    let shadowed: Vec<Light> = lights
+                        .iter()
+                        .map(|l| {
+                            let result = march(start, l.dir, far, tube, &self.march, sdf);
+                            Light { intensity: l.intensity * soft_shadow(&result, softness), ..*l }
+                        })
+                        .collect();
+                    illuminate(&shadowed, Floor::NORMAL, cfg.wrap).0
+                }
+                None => illuminate(&lights, Floor::NORMAL, cfg.wrap).0,
+            };
+            lit.max(min_col) * check * floor.visibility(k)
+        };
+        // light seen along mirror bounces off a hit at `p` with normal `n`,
+        // reached along `rd`: every later surface keeps `1 - reflectivity` of
+        // its diffuse light and passes the rest on to the next bounce, the
+        // last one keeps all of it. Escaping rays add nothing
+        let reflected = |mut p: Vec3, mut n: Vec3, mut rd: Vec3| {
+            let (mut light, mut weight) = (0.0, 1.0);
+            for bounce in 1..=cfg.max_bounces {
+                rd = rd.reflect(n);
+                let start = p.add(n.mul(tube));
+                let floor_k = self.floor.and_then(|floor| floor.intersect(start, rd));
+                let far = floor_k.map_or(far, |fk| fk.min(far));
+                let hit = march(start, rd, far, tube, &self.march, sdf).depth;
+                let (lit, q, normal) = match (hit, self.floor, floor_k) {
+                    (Some(k), _, _) => {
+                        let q = start.add(rd.mul(k));
+                        let normal = estimate_normal(q, sdf);
+                        (illuminate(&lights, normal, cfg.wrap).0.max(min_col), q, normal)
+                    }
+                    (None, Some(floor), Some(fk)) => {
+                        let q = start.add(rd.mul(fk));
+                        (floor_light(floor, q, fk), q, Floor::NORMAL)
+                    }
+                    _ => break,
+                };
+                let share = if bounce == cfg.max_bounces { 1.0 } else { 1.0 - cfg.reflectivity };
+                light += weight * share * lit;
+                weight *= cfg.reflectivity;
+                (p, n) = (q, normal);
+            }
+            light
+        };
+
+        // the normal of a hit at `point` seen along `rd`
+        let surface_normal = |point: Vec3, rd: Vec3| estimate_hit_normal(point, rd, cfg.normal_offset, sdf);
+
+        // with --taa every frame's rays pass through another point of their
+        // cell, up to half a cell from the center
+        let (jx, jy) = if cfg.taa {
+            let k = t % TAA_OFFSETS + 1;
+            (halton(k, 2) - 0.5, halton(k, 3) - 0.5)
+        } else {
+            (0.0, 0.0)
+        };
+
+        // first pass: what each marched cell's ray hits, without shading
+        let geometry = |x: f32, y: f32| {
+            let (rd, floor_k, result) = trace(x, y);
+            let surface = result.depth.map(|depth| {
+                let point = ro.add(rd.mul(depth));
+                let object = scene.closest(to_object(point.mul(1.0 / scale))).1;
+                // flat normals come from the neighbours once all points are in
+                let normal = if cfg.flat { Vec3::default() } else { surface_normal(point, rd) };
+                Surface { depth, point, normal, object }
+            });
+            Geometry { rd, floor_k, march: result, surface }
+        };
+        let fill_geometry = |idx0: usize, cells: &mut [Option<Geometry>]| {
+            for (n, g) in cells.iter_mut().enumerate() {
+                let (i, j) = ((idx0 + n) % w, (idx0 + n) / w);
+                if marched(i, j) {
+                    *g = Some(geometry(i as f32 + jx, j as f32 + jy));
+                }
+            }
+        };
+        let mut gbuffer = vec![None; w * h];
+        // rows per thread, for both passes
+        #[cfg(feature = "threads")]
+        let band = (height as usize).div_ceil(self.threads.max(1)).max(1);
+
+        #[cfg(not(feature = "threads"))]
+        fill_geometry(0, &mut gbuffer);
+
+        #[cfg(feature = "threads")]
+        std::thread::scope(|s| {
+            for (k, cells) in gbuffer.chunks_mut(band * w).enumerate() {
+                let fill_geometry = &fill_geometry;
+                s.spawn(move || fill_geometry(k * band * w, cells));
+            }
+        });
+
+        // flat shading takes each normal from the neighbouring hit points
+        // instead of the SDF gradient
+        if cfg.flat {
+            let points: Vec<_> =
+                gbuffer.iter().map(|g| g.and_then(|g| g.surface).map(|s| s.point)).collect();
+            let faces = face_normals(&points, w, h, block, ro);
+            for (g, face) in gbuffer.iter_mut().zip(faces) {
+                if let Some(Geometry { rd, surface: Some(surface), .. }) = g.as_mut() {
+                    surface.normal = face.unwrap_or_else(|| surface_normal(surface.point, *rd));
+                }
+            }
+        }
+
+        let traced = Instant::now();
+
+        // second pass, one cell at a time: glyph, color, hit depth and normal
+        // of cell (i, j) seeing `geometry`
+        let shade = |i: u16, j: u16, geometry: Geometry| {
+            let Geometry { rd, floor_k, march: result, surface } = geometry;
+
+            let mut diff = 0.0_f32;
+            // highlights kept apart from diffuse for --specular-color
+            let mut spec = 0.0_f32;
+            let mut material = None;
+            let mut tint = None;
+            let mut uv = None;
+            let mut hit = false;
+            let mut depth = f32::INFINITY;
+            let mut normal = Vec3::default();
+            if let Some(Surface { depth: k, point: p, normal: n, object }) = surface {
+                let (lit, light_color) = illuminate(&lights, n, cfg.wrap);
+                diff += lit.max(min_col);
+                if let Some(model) = cfg.specular_model {
+                    let s = highlights(&lights, model, n, rd.mul(-1.0), cfg.shininess);
+                    match cfg.specular_color {
+                        Some(_) => spec = s,
+                        None => diff += s,
+                    }
+                }
+                if let Some(ior) = cfg.ior {
+                    // one refraction bounce: step out of the object along the
+                    // bent ray, then take the diffuse light of what it hits
+                    // behind, or darkness when it escapes
+                    let behind = rd.refract(n, 1.0 / ior).map_or(0.0, |rt| {
+                        let mut out = tube;
+                        while out < far && sdf(p.add(rt.mul(out))) < tube {
+                            out += tube;
+                        }
+                        let start = p.add(rt.mul(out));
+                        march(start, rt, far, tube, &self.march, sdf).depth.map_or(0.0, |k| {
+                            let q = start.add(rt.mul(k));
+                            illuminate(&lights, estimate_normal(q, sdf), cfg.wrap).0.max(min_col)
+                        })
+                    });
+                    diff = mix(diff, behind, cfg.transmission);
+                }
+                if cfg.max_bounces > 0 {
+                    diff = mix(diff, reflected(p, n, rd), cfg.reflectivity);
+                }
+                tint = Some(light_color);
+                hit = true;
+                depth = k;
+                normal = n;
+                let obj = &scene.objects[object];
+                material = obj.color;
+                uv = obj.uv(to_object(p.mul(1.0 / scale)));
+                // the plane is nearer than the body where it was cut
+                if clip.is_some_and(|(n, offset)| sd_plane(p, n, offset) > body(p)) {
+                    material = Some(cfg.cap_color);
+                    uv = None;
+                }
+            }
+            if let (false, Some(floor), Some(fk)) = (hit, self.floor, floor_k) {
+                let p = ro.add(rd.mul(fk));
+                diff = floor_light(floor, p, fk);
+                if cfg.max_bounces > 0 {
+                    diff = mix(diff, reflected(p, Floor::NORMAL, rd), cfg.reflectivity);
+                }
+                tint = Some(illuminate(&lights, Floor::NORMAL, cfg.wrap).1);
+                hit = true;
+                depth = fk;
+                normal = Floor::NORMAL;
+                material = Some(Color::Rgb { r: 220, g: 220, b: 220 });
+            }
+
+            // up to half a glyph step of noise that changes every frame, so
+            // bands between glyphs average out
+            let jitter = if cfg.jitter && hit { cell_jitter(i, j, t, cfg.seed()) } else { 0.0 };
+            let mut ci = ((diff + spec) * 20.0 + jitter) as i32;
+            if ci < 0 { ci = 0; }
+            if ci > grad_size { ci = grad_size; }
+            let px = GLYPHS[ci as usize];
+
+            // Calculate color based on lighting intensity with better blending
+            let raw_intensity = diff / COLOR_SCALE; // More sensitive to lighting changes
+            // Ensure minimum brightness, unless the wrapped falloff already softens the dark side
+            let floor = if cfg.wrap > 0.0 { 0.0 } else { 0.1 };
+            let intensity = cfg.tonemap.apply(raw_intensity).clamp(floor, 1.0);
+            let intensity = adjust_intensity(intensity, cfg.contrast, cfg.brightness);
+            // a color map moves the palette lookup toward the cell's place on
+            // screen, while the glyph keeps showing the light
+            let (span_x, span_y) = (width.saturating_sub(1).max(1), height.saturating_sub(1).max(1));
+            let (fx, fy) = (i as f32 / span_x as f32, j as f32 / span_y as f32);
+            let lookup = match cfg.color_map.coordinate(fx, fy) {
+                Some(at) => mix(intensity, at, cfg.color_map_mix),
+                None => intensity,
+            };
+            let color = match material {
+                Some(c) => lerp_color(&Color::Rgb { r: 0, g: 0, b: 0 }, &rotate_hue(c, hue), intensity),
+                None => palette.get(lookup),
+            };
+            let color = match uv {
+                Some((u, v)) => cfg.pattern.apply(color, u, v, cfg.pattern_scale),
+                None => color,
+            };
+            let color = tint.map_or(color, |t| apply_tint(color, t));
+            let color = match cfg.specular_color {
+                Some(c) if spec > 0.0 => add_light(color, c, spec, cfg.tonemap),
+                _ => color,
+            };
+
+            let color = if hit && cfg.fog_density > 0.0 {
+                lerp_color(&color, &cfg.fog_color, fog_factor(cfg.fog_density, depth))
+            } else {
+                color
+            };
+
+            // misses keep the terminal's own background, unless they went
+            // through the donut's hole and that has a backdrop of its own
+            let hole = cfg
+                .hole_color
+                .filter(|_| !hit && scene.through_hole(to_object(ro.mul(1.0 / scale)), to_object(rd)));
+            let (px, color) = match (self.stars.star(i, j, t), hole) {
+                _ if hit && mode == RenderMode::Normals => (px, normal_color(normal)),
+                _ if hit => (px, color),
+                (_, Some(backdrop)) => (HOLE_GLYPH, backdrop),
+                (Some(star), None) if mode != RenderMode::Normals => star,
+                _ => (px, Color::Reset),
+            };
+            // the debug views take glyph and color from how near the hit is
+            // or how hard its ray was to march, of a whole fixed-step miss
+            let level = |share: f32| GLYPHS[(share.clamp(0.0, 1.0) * grad_size as f32).round() as usize];
+            let (px, color) = match mode {
+                RenderMode::Depth if hit => {
+                    let near = (camera.distance - scene.extent * scale).max(0.0);
+                    let nearness = ((far - depth) / (far - near)).clamp(0.0, 1.0);
+                    let gray = (40.0 + 215.0 * nearness) as u8;
+                    (level(nearness), Color::Rgb { r: gray, g: gray, b: gray })
+                }
+                RenderMode::Steps => {
+                    let share = result.steps as f32 * tube / far;
+                    (level(share), sample_gradient(GRADIENT, share))
+                }
+                _ => (px, color),
+            };
+            // a near miss covers part of the cell
+            let coverage = coverage(&result, tube, 2.0 / height as f32);
+            (px, color, depth, normal, result.steps, coverage)
+        };
+
+        // fills whole rows starting at row `j0`
+        let fill_rows = |j0: usize, rows: Rows| {
+            for idx in 0..rows.chars.len() {
+                let (i, j) = (idx % w, j0 + idx / w);
+                if !marched(i, j) {
+                    continue;
+                }
+                let geometry = gbuffer[i + j * w].expect("geometry is filled for every marched cell");
+                let (px, color, depth, normal, steps, coverage) = shade(i as u16, j as u16, geometry);
+                rows.chars[idx] = px;
+                rows.colors[idx] = color;
+                rows.depth[idx] = depth;
+                rows.normals[idx] = normal;
+                rows.steps[idx] = steps;
+                rows.coverage[idx] = coverage;
+            }
+        };
+
+        #[cfg(not(feature = "threads"))]
+        frame.bands(height as usize).for_each(|rows| fill_rows(0, rows));
+
+        // hand each thread a disjoint band of rows
+        #[cfg(feature = "threads")]
+        std::thread::scope(|s| {
+            for (k, rows) in frame.bands(band).enumerate() {
+                let fill_rows = &fill_rows;
+                s.spawn(move || fill_rows(k * band, rows));
+            }
+        });
+        frame.replicate_blocks(block);
+
+        // edge-only anti-aliasing: cells along a silhouette average their
+        // glyph level and color with four more rays a quarter cell off center,
+        // misses counting as dark, while the rest keep their single ray
+        if cfg.antialias_edges_only {
+            let edges = silhouette_cells(&frame.depth, w, h);
+            for idx in (0..w * h).filter(|&idx| edges[idx]) {
+                let (i, j) = (idx % w, idx / w);
+                let level = |c: u8| GLYPHS.iter().position(|&g| g == c).unwrap_or(0) as f32;
+                let (mut sum, mut rgb) = (level(frame.chars[idx]), [0.0; 3]);
+                let mut hits = 0;
+                let mut add = |color: Color, hit: bool| {
+                    if hit {
+                        let (r, g, b) = to_rgb(color);
+                        rgb = [rgb[0] + r as f32, rgb[1] + g as f32, rgb[2] + b as f32];
+                        hits += 1;
+                    }
+                };
+                add(frame.colors[idx], frame.depth[idx].is_finite());
+                for (dx, dy) in [(-0.25, -0.25), (0.25, -0.25), (-0.25, 0.25), (0.25, 0.25)] {
+                    let mut g = geometry(i as f32 + dx, j as f32 + dy);
+                    // flat normals need whole-frame neighbours, so samples
+                    // between cells take the gradient instead
+                    if let Some(surface) = g.surface.as_mut().filter(|_| cfg.flat) {
+                        surface.normal = surface_normal(surface.point, g.rd);
+                    }
+                    let (px, color, depth, ..) = shade(i as u16, j as u16, g);
+                    sum += level(px);
+                    add(color, depth.is_finite());
+                }
+                if hits > 0 {
+                    let share = hits as f32 / 5.0;
+                    let [r, g, b] = rgb.map(|c| (c / hits as f32 * share) as u8);
+                    frame.chars[idx] = GLYPHS[((sum / 5.0).round() as usize).min(GLYPHS.len() - 1)];
+                    frame.colors[idx] = Color::Rgb { r, g, b };
+                }
+            }
+        }
+        let shaded = Instant::now();
+
+        // debug view: the visible surface laid out flat by its (u, v)
+        if cfg.uv_view {
+            let uvs: Vec<_> = gbuffer
+                .iter()
+                .map(|g| {
+                    let surface = g.and_then(|g| g.surface)?;
+                    scene.objects[surface.object].uv(to_object(surface.point.mul(1.0 / scale)))
+                })
+                .collect();
+            let (chars, colors) = unwrap_uv(&frame.chars, &frame.colors, &uvs, w, h);
+            frame.clear();
+            (frame.chars, frame.colors) = (chars, colors);
+        }
+
+        // the jittered frames average out into the supersampled image; the
+        // history isn't reprojected, which holds up while the scene barely
+        // moves between frames
+        if cfg.taa {
+            blend_history(&mut self.history, frame, TAA_BLEND);
+        }
+
+        if let Some(alpha) = cfg.accumulate {
+            blend_history(&mut self.accum, frame, alpha);
+        }
+
+        if cfg.aa {
+            let blends = silhouette_blend(&frame.depth, &frame.coverage, w, h);
+            for (idx, blend) in blends.into_iter().enumerate() {
+                if let Some((src, coverage)) = blend.filter(|_| frame.chars[idx] == b' ') {
+                    // a dimmer copy of the neighbouring hit
+                    let level = GLYPHS.iter().position(|&g| g == frame.chars[src]).unwrap_or(0);
+                    frame.chars[idx] = GLYPHS[(level as f32 * coverage).round() as usize];
+                    frame.colors[idx] =
+                        lerp_color(&Color::Rgb { r: 0, g: 0, b: 0 }, &frame.colors[src], coverage);
+                }
+            }
+        }
+
+        if let Some(focus) = cfg.focus {
+            let levels: Vec<f32> = frame
+                .chars
+                .iter()
+                .map(|&c| GLYPHS.iter().position(|&g| g == c).unwrap_or(0) as f32)
+                .collect();
+            let blurred = depth_of_field(&levels, &frame.colors, &frame.depth, w, h, focus, cfg.aperture);
+            for (idx, blur) in blurred.into_iter().enumerate() {
+                if let Some((level, color)) = blur {
+                    frame.chars[idx] = GLYPHS[(level.round() as usize).min(GLYPHS.len() - 1)];
+                    frame.colors[idx] = color;
+                }
+            }
+        }
+
+        if let Some(threshold) = cfg.bloom {
+            let background: Vec<bool> = frame
+                .depth
+                .iter()
+                .zip(&frame.chars)
+                .map(|(d, &c)| !d.is_finite() && c == b' ')
+                .collect();
+            let glow = bloom(
+                &frame.colors,
+                &background,
+                width as usize,
+                height as usize,
+                threshold,
+                cfg.bloom_radius,
+            );
+            for (idx, c) in glow.into_iter().enumerate() {
+                if let Some(c) = c {
+                    // a fainter glyph for the outer glow
+                    frame.chars[idx] = if luminance(c) > 0.25 { b':' } else { b'.' };
+                    frame.colors[idx] = c;
+                }
+            }
+        }
+
+        if cfg.outline || self.mode == RenderMode::Outline {
+            let edges = detect_edges(
+                &frame.depth,
+                &frame.normals,
+                width as usize,
+                height as usize,
+                // hit depths are quantized to whole march steps
+                self.edge_depth * scene.step * scale,
+                self.edge_normal,
+            );
+            for (idx, _) in edges.iter().enumerate().filter(|(_, e)| **e) {
+                frame.chars[idx] = b'#';
+                frame.colors[idx] = Color::Rgb { r: 255, g: 255, b: 255 };
+            }
+        }
+
+        if let Some(text) = &cfg.banner {
+            let (w, h) = (width as i32, height as i32);
+            let text_w = text_width(text);
+            // scroll in from the right edge and wrap once fully off the left
+            let x = if cfg.banner_scroll == 0.0 {
+                (w - text_w) / 2
+            } else {
+                w - ((t as f32 * cfg.banner_scroll) as i32).rem_euclid(w + text_w)
+            };
+            let y = (h - GLYPH_HEIGHT) / 2;
+            for (cx, cy) in text_cells(text, x, y) {
+                if cx < 0 || cy < 0 || cx >= w || cy >= h {
+                    continue;
+                }
+                let idx = cx as usize + cy as usize * width as usize;
+                // only over the background, so the donut passes in front
+                if !frame.depth[idx].is_finite() {
+                    frame.chars[idx] = b'#';
+                    frame.colors[idx] = GRADIENT.last().unwrap().1;
+                }
+            }
+        }
+        self.march_logged = cfg.march_log.is_some();
+        self.stage_times = StageTimes {
+            march: traced - started,
+            shade: shaded - traced,
+            post: shaded.elapsed(),
+            write: Duration::ZERO,
+        };
+    }
+}
+
+/// Fills `frame` with frame `t` of `renderer` without touching the terminal;
+/// the binary's loops draw what this leaves behind. Keeping the renderer
+/// between calls animates the time-driven effects (spin, pulse, ripple, ...)
+/// and pays its setup once.
+pub fn render_frame_into(renderer: &mut Renderer, t: u32, frame: &mut Frame) {
+    renderer.render(t, frame);
+}
+
+/// Renders a single frame with the scene spun to `angle` (radians) and
+/// returns its glyph and color buffers, row-major. A one-off: time-driven
+/// effects are at frame 0; use `render_frame_into` to animate.
+pub fn render_frame(cfg: &Config, angle: f32, width: u16, height: u16) -> (Vec<u8>, Vec<Color>) {
+    let mut renderer = Renderer::new(cfg);
+    renderer.spin_base = (0, angle);
+    let mut frame = Frame::new(width, height);
+    render_frame_into(&mut renderer, 0, &mut frame);
+    (frame.chars, frame.colors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output::{write_text, LineEnding};
+    use crate::stats::checksum;
+    use crate::scene::{Object, Shape, Transform};
+
+    fn render_frame_at(cfg: &Config, t: u32) -> Frame {
+        let mut frame = Frame::new(80, 24);
+        Renderer::new(cfg).render(t, &mut frame);
+        frame
+    }
+
+    #[test]
+    fn frame_100_headless_is_stable() {
+        let cfg = Config::from_args(["--frame", "100", "--headless"].map(String::from)).unwrap();
+        let frame = render_frame_at(&cfg, 100);
+        // what `--frame 100 --headless --checksum` printed when this was written
+        assert_eq!(checksum(&frame), 0xb5d5_cff3_a62c_323f);
+        let text = |frame: &Frame| {
+            let mut out = Vec::new();
+            write_text(&mut out, frame, LineEnding::Lf).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(text(&render_frame_at(&cfg, 100)), text(&frame));
+        // the same picture the animation reaches at that frame
+        let mut renderer = Renderer::new(&cfg);
+        let mut animated = Frame::new(80, 24);
+        for t in 0..=100 {
+            renderer.render(t, &mut animated);
+        }
+        assert_eq!(text(&animated), text(&frame));
+    }
+
+    #[test]
+    fn same_seed_renders_the_same_frame() {
+        let seeded = |seed: &str| {
+            let args = ["--seed", seed, "--stars", "0.05", "--jitter", "--shake", "0.2"];
+            Config::from_args(args.map(String::from)).unwrap()
+        };
+        let frame = |cfg: &Config| checksum(&render_frame_at(cfg, 30));
+        assert_eq!(frame(&seeded("5")), frame(&seeded("5")));
+        assert_ne!(frame(&seeded("5")), frame(&seeded("6")));
+        let saver = |seed: &str| Config { screensaver: true, ..seeded(seed) };
+        assert_eq!(frame(&saver("5")), frame(&saver("5")));
+    }
+
+    #[cfg(feature = "threads")]
+    #[test]
+    fn threaded_render_matches_the_serial_one() {
+        let cfg = Config::from_args(["--aa", "--outline", "--shadows", "0.5"].map(String::from)).unwrap();
+        let with_threads = |threads: usize| {
+            let mut renderer = Renderer::new(&cfg);
+            renderer.threads = threads;
+            let mut frame = Frame::new(80, 24);
+            renderer.render(42, &mut frame);
+            frame
+        };
+        let serial = with_threads(1);
+        for threads in [2, 3, 8, 40] {
+            let threaded = with_threads(threads);
+            assert_eq!(threaded.chars, serial.chars, "{threads} threads");
+            assert_eq!(threaded.colors, serial.colors, "{threads} threads");
+        }
+    }
+
+    #[test]
+    fn render_frame_fills_both_buffers() {
+        let cfg = Config::default();
+        let (chars, colors) = render_frame(&cfg, 0.0, 40, 20);
+        assert_eq!((chars.len(), colors.len()), (800, 800));
+        // the corners stay empty, the middle of the donut's body is lit
+        for idx in [0, 39, 760, 799] {
+            assert_eq!((chars[idx], colors[idx]), (b' ', Color::Reset));
+        }
+        let body = 14 + 12 * 40;
+        assert_ne!(chars[body], b' ');
+        assert!(matches!(colors[body], Color::Rgb { .. }));
+        assert_ne!(render_frame(&cfg, 1.0, 40, 20).0, chars);
+    }
+
+    #[test]
+    fn subsample_two_replicates_each_marched_cell() {
+        // odd sizes leave partial blocks at the right and bottom edges
+        let (w, h) = (5usize, 3usize);
+        let mut frame = Frame::new(w as u16, h as u16);
+        for j in (0..h).step_by(2) {
+            for i in (0..w).step_by(2) {
+                let idx = i + j * w;
+                frame.chars[idx] = b'a' + idx as u8;
+                frame.depth[idx] = idx as f32;
+                frame.steps[idx] = 1;
+            }
+        }
+        frame.replicate_blocks(2);
+        for j in 0..h {
+            for i in 0..w {
+                let (idx, src) = (i + j * w, (i & !1) + (j & !1) * w);
+                assert_eq!(frame.chars[idx], b'a' + src as u8, "cell ({i}, {j})");
+                assert_eq!(frame.depth[idx], src as f32);
+                assert_eq!(frame.steps[idx], u32::from(idx == src));
+            }
+        }
+    }
+
+    #[test]
+    fn headlamp_turns_with_the_orbiting_camera() {
+        let cfg = Config::from_args(["--headlamp"].map(String::from)).unwrap();
+        let mut renderer = Renderer::new(&cfg);
+        let before = renderer.world_lights(0, renderer.camera.basis());
+        renderer.camera.orbit(0.7, 0.0);
+        let after = renderer.world_lights(0, renderer.camera.basis());
+        for (b, a) in before.iter().zip(&after) {
+            // an azimuth orbit turns the camera, and so the light, about Z
+            assert!(a.dir.sub(Mat3::rotation_z(0.7) * b.dir).len() < 1e-5);
+            // while staying put relative to the camera
+            assert!((renderer.camera.basis().transpose() * a.dir).sub(b.dir).len() < 1e-5);
+        }
+        // without it the lights stay put in the world
+        let mut fixed = Renderer::new(&Config::default());
+        let before = fixed.world_lights(0, fixed.camera.basis());
+        fixed.camera.orbit(0.7, 0.0);
+        assert_eq!(fixed.world_lights(0, fixed.camera.basis()), before);
+    }
+
+    #[test]
+    fn checksum_tracks_the_rendered_picture() {
+        let cfg = Config::default();
+        let reference = checksum(&render_frame_at(&cfg, 30));
+        assert_eq!(checksum(&render_frame_at(&cfg, 30)), reference);
+        assert_ne!(checksum(&render_frame_at(&cfg, 31)), reference);
+        let neon = Config::from_args(["--theme", "neon"].map(String::from)).unwrap();
+        assert_ne!(checksum(&render_frame_at(&neon, 30)), reference);
+    }
+
+    #[test]
+    fn gbuffer_normals_of_a_sphere_point_away_from_its_center() {
+        let radius = 0.8;
+        let mut renderer = Renderer::new(&Config::default());
+        renderer.scene = Scene {
+            objects: vec![Object {
+                shape: Shape::Sphere { radius },
+                transform: Transform::identity(),
+                color: None,
+                spin: None,
+            }],
+            step: 0.01,
+            extent: radius,
+            blend: 0.0,
+        };
+        let mut frame = Frame::new(40, 20);
+        renderer.render(0, &mut frame);
+        let eye = renderer.camera.position();
+        let mut hits = 0;
+        for (&depth, &n) in frame.depth.iter().zip(&frame.normals).filter(|(d, _)| d.is_finite()) {
+            assert!((n.len() - 1.0).abs() < 1e-3);
+            // an outward normal scaled by the radius is the hit point itself
+            assert!((n.mul(radius).sub(eye).len() - depth).abs() < 0.05, "{n:?} at {depth}");
+            hits += 1;
+        }
+        assert!(hits > 50, "{hits} hits");
+    }
+
+    #[test]
+    fn bounces_off_a_mirror_floor_change_the_picture() {
+        let rendered = |args: &[&str]| {
+            let args = ["--floor", "0.5", "--reflectivity", "0.6"].iter().chain(args).map(|s| s.to_string());
+            checksum(&render_frame_at(&Config::from_args(args).unwrap(), 40))
+        };
+        let direct = rendered(&[]);
+        assert_eq!(rendered(&["--max-bounces", "0"]), direct);
+        let one = rendered(&["--max-bounces", "1"]);
+        assert_ne!(one, direct);
+        assert_ne!(rendered(&["--max-bounces", "2"]), one);
+    }
+
+    #[test]
+    fn ping_pong_is_a_symmetric_triangle_wave() {
+        let period = 5;
+        let wave: Vec<u32> = (0..2 * period * 2).map(|t| ping_pong(t, period)).collect();
+        assert_eq!(wave, [0, 1, 2, 3, 4, 5, 4, 3, 2, 1, 0, 1, 2, 3, 4, 5, 4, 3, 2, 1]);
+        // mirrored about the turning point, and it loops after two periods
+        for t in 0..=period {
+            assert_eq!(ping_pong(period - t, period), ping_pong(period + t, period));
+            assert_eq!(ping_pong(t, period), ping_pong(t + 2 * period, period));
+        }
+        assert!((0..20).all(|t| ping_pong(t, 0) == 0));
+    }
+
+    #[test]
+    fn seeking_to_a_frame_range_matches_the_full_run() {
+        for extra in [&[][..], &["--screensaver"][..]] {
+            let args = ["--start-frame", "100", "--end-frame", "110"].iter().chain(extra);
+            let cfg = Config::from_args(args.map(|s| s.to_string())).unwrap();
+            assert_eq!(cfg.frame_range(), 100..110);
+            let render_all = |frames: std::ops::Range<u32>| {
+                let (mut renderer, mut frame) = (Renderer::new(&cfg), Frame::new(40, 12));
+                frames
+                    .map(|t| {
+                        renderer.render(t, &mut frame);
+                        checksum(&frame)
+                    })
+                    .collect::<Vec<_>>()
+            };
+            let full = render_all(0..110);
+            assert_eq!(render_all(cfg.frame_range()), full[100..], "{extra:?}");
+        }
+    }
+
+    #[test]
+    fn taa_converges_toward_the_average_of_its_jittered_frames() {
+        // a still, unpulsing donut, so the history never needs reprojecting
+        let cfg = Config::from_args(["--taa".to_string()]).unwrap();
+        let still = || {
+            let mut renderer = Renderer::new(&cfg);
+            renderer.set_speed(0, 0.0);
+            renderer.pulse_amp = 0.0;
+            renderer
+        };
+        let levels = |frame: &Frame| -> Vec<f32> {
+            frame.chars.iter().map(|&c| GLYPHS.iter().position(|&g| g == c).unwrap() as f32).collect()
+        };
+        // a fresh renderer has no history yet, so its frame t is the raw
+        // single sample at offset t; averaged, they are the supersampled image
+        let samples: Vec<Vec<f32>> = (0..TAA_OFFSETS)
+            .map(|t| {
+                let mut frame = Frame::new(40, 12);
+                still().render(t, &mut frame);
+                levels(&frame)
+            })
+            .collect();
+        let reference: Vec<f32> = (0..samples[0].len())
+            .map(|idx| samples.iter().map(|s| s[idx]).sum::<f32>() / TAA_OFFSETS as f32)
+            .collect();
+        let error = |levels: &[f32]| -> f32 {
+            levels.iter().zip(&reference).map(|(a, b)| (a - b).abs()).sum()
+        };
+
+        let (mut renderer, mut frame) = (still(), Frame::new(40, 12));
+        let mut errors = Vec::new();
+        for t in 0..4 * TAA_OFFSETS {
+            renderer.render(t, &mut frame);
+            errors.push(error(&levels(&frame)));
+        }
+        let (first, last) = (errors[0], errors[errors.len() - 1]);
+        assert!(first > 0.0 && last < 0.6 * first, "{errors:?}");
+    }
+
+    #[test]
+    fn mode_cycling_visits_every_mode_and_wraps_to_the_first() {
+        let mut mode = RenderMode::default();
+        let mut seen = Vec::new();
+        for _ in 0..RenderMode::ALL.len() {
+            seen.push(mode);
+            mode = mode.next();
+        }
+        assert_eq!(seen, RenderMode::ALL);
+        assert_eq!(mode, RenderMode::Shaded);
+        assert_eq!(RenderMode::Outline.next(), RenderMode::Shaded);
+    }
+
+    #[test]
+    fn render_frame_into_animates_a_kept_renderer() {
+        let cfg = Config::default();
+        let (mut renderer, mut frame) = (Renderer::new(&cfg), Frame::new(40, 20));
+        render_frame_into(&mut renderer, 0, &mut frame);
+        // a fresh renderer at frame 0 is the one-off render at the start angle
+        assert_eq!((frame.chars.clone(), frame.colors.clone()), render_frame(&cfg, 0.0, 40, 20));
+        let first = checksum(&frame);
+        render_frame_into(&mut renderer, 30, &mut frame);
+        assert_ne!(checksum(&frame), first);
+    }
+
+    #[test]
+    fn empty_frames_render_to_empty_buffers() {
+        let cfg = Config::from_args(["--color-map", "radial"].map(String::from)).unwrap();
+        for (width, height) in [(0, 4), (4, 0), (0, 0), (1, 1)] {
+            let (chars, colors) = render_frame(&cfg, 0.0, width, height);
+            assert_eq!((chars.len(), colors.len()), (width as usize * height as usize, chars.len()));
+        }
+    }
+}
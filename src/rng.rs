@@ -0,0 +1,85 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::starfield::hash_cell;
+
+/// Small, seedable SplitMix64 generator so randomized renders are
+/// reproducible without pulling in a dependency.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, 1)`.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Uniform in `[lo, hi)`.
+    pub fn range(&mut self, lo: f32, hi: f32) -> f32 {
+        lo + (hi - lo) * self.next_f32()
+    }
+
+    /// Uniform integer in `[lo, hi]`.
+    pub fn range_usize(&mut self, lo: usize, hi: usize) -> usize {
+        lo + (self.next_u64() % (hi - lo + 1) as u64) as usize
+    }
+}
+
+/// Seed from the wall clock, for runs that didn't ask for a specific one.
+pub fn time_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default()
+}
+
+/// Element `index` of the Halton low-discrepancy sequence in `base`, in
+/// `[0, 1)`: successive values fill the interval evenly.
+pub fn halton(mut index: u32, base: u32) -> f32 {
+    let (mut value, mut scale) = (0.0, 1.0);
+    while index > 0 {
+        scale /= base as f32;
+        value += scale * (index % base) as f32;
+        index /= base;
+    }
+    value
+}
+
+/// Zero-mean noise in `[-0.5, 0.5)` for cell (i, j) at `frame`, the same
+/// for the same seed.
+pub fn cell_jitter(i: u16, j: u16, frame: u32, seed: u64) -> f32 {
+    let h = hash_cell(i, j, seed ^ (frame as u64).wrapping_mul(0xd1b5_4a32_d192_ed03));
+    (h >> 40) as f32 / (1u64 << 24) as f32 - 0.5
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cell_jitter_is_zero_mean_and_seeded() {
+        let samples: Vec<f32> = (0..64u16)
+            .flat_map(|i| (0..64u16).map(move |j| cell_jitter(i, j, 7, 42)))
+            .collect();
+        assert!(samples.iter().all(|s| (-0.5..0.5).contains(s)));
+        let mean = samples.iter().sum::<f32>() / samples.len() as f32;
+        assert!(mean.abs() < 0.02, "mean {mean}");
+
+        assert_eq!(cell_jitter(3, 5, 7, 42), cell_jitter(3, 5, 7, 42));
+        assert_ne!(cell_jitter(3, 5, 7, 42), cell_jitter(3, 5, 7, 43));
+        assert_ne!(cell_jitter(3, 5, 7, 42), cell_jitter(3, 5, 8, 42));
+    }
+}
@@ -0,0 +1,410 @@
+use crossterm::style::Color;
+
+use crate::color::GRADIENT;
+use crate::font::{text_cells, text_width, GLYPH_HEIGHT};
+use crate::math::{Quat, Vec2, Vec3};
+use crate::sdf::{op_smooth_union, sd_box, sd_sphere, sd_torus, sd_torus_knot, torus_uv};
+
+#[derive(Copy, Clone, Debug)]
+pub enum Shape {
+    Torus { radii: Vec2, axis: Vec3 },
+    Sphere { radius: f32 },
+    // radii are the major circle and the winding around it
+    Knot { knot: (u32, u32), radii: Vec2, tube: f32, axis: Vec3 },
+    Box { half_extents: Vec3 },
+}
+
+impl Shape {
+    pub fn distance(&self, p: Vec3) -> f32 {
+        match *self {
+            Shape::Torus { radii, axis } => sd_torus(p, radii, axis),
+            Shape::Sphere { radius } => sd_sphere(p, radius),
+            Shape::Knot { knot, radii, tube, axis } => sd_torus_knot(p, knot, radii, tube, axis),
+            Shape::Box { half_extents } => sd_box(p, half_extents),
+        }
+    }
+
+    /// Radius of a sphere around the origin that holds the whole shape.
+    pub fn bounding_radius(&self) -> f32 {
+        match *self {
+            Shape::Torus { radii, .. } => radii.x + radii.y,
+            Shape::Sphere { radius } => radius,
+            Shape::Knot { radii, tube, .. } => radii.x + radii.y + tube,
+            Shape::Box { half_extents } => half_extents.len(),
+        }
+    }
+
+    /// Surface (u, v) coordinates, for shapes that have a parameterization.
+    pub fn uv(&self, p: Vec3) -> Option<(f32, f32)> {
+        match *self {
+            Shape::Torus { radii, axis } => Some(torus_uv(p, radii, axis)),
+            Shape::Sphere { .. } | Shape::Knot { .. } | Shape::Box { .. } => None,
+        }
+    }
+
+    /// Whether the ray from `ro` along `rd` crosses the ring's plane within
+    /// its major radius. For a ray that missed the shape, that means it went
+    /// through the hole in the middle.
+    pub fn through_hole(&self, ro: Vec3, rd: Vec3) -> bool {
+        let (radius, axis) = match *self {
+            Shape::Torus { radii, axis } | Shape::Knot { radii, axis, .. } => (radii.x, axis),
+            Shape::Sphere { .. } | Shape::Box { .. } => return false,
+        };
+        let facing = rd.dot(axis);
+        if facing.abs() < 1e-6 {
+            return false;
+        }
+        let s = -ro.dot(axis) / facing;
+        s > 0.0 && ro.add(rd.mul(s)).len() < radius
+    }
+}
+
+/// Places a shape: scaled uniformly, then rotated, then translated.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: f32,
+}
+
+impl Transform {
+    pub fn identity() -> Self {
+        Self { translation: Vec3::default(), rotation: Quat::identity(), scale: 1.0 }
+    }
+
+    pub fn translate(translation: Vec3) -> Self {
+        Self { translation, ..Self::identity() }
+    }
+
+    /// World point `p` in the shape's own space.
+    pub fn to_local(&self, p: Vec3) -> Vec3 {
+        self.rotation
+            .conjugate()
+            .rotate(p.sub(self.translation))
+            .mul(1.0 / self.scale)
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Object {
+    pub shape: Shape,
+    pub transform: Transform,
+    // None shades with the intensity gradient, Some tints by that color
+    pub color: Option<Color>,
+    // extra turn of its own: axis, and angle as a multiple of the scene's spin
+    pub spin: Option<(Vec3, f32)>,
+}
+
+impl Object {
+    pub fn distance(&self, p: Vec3) -> f32 {
+        // uniform scale keeps it a distance bound, in world units
+        self.shape.distance(self.transform.to_local(p)) * self.transform.scale
+    }
+
+    pub fn uv(&self, p: Vec3) -> Option<(f32, f32)> {
+        self.shape.uv(self.transform.to_local(p))
+    }
+
+    pub fn through_hole(&self, ro: Vec3, rd: Vec3) -> bool {
+        self.shape.through_hole(self.transform.to_local(ro), self.transform.rotation.conjugate().rotate(rd))
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Scene {
+    pub objects: Vec<Object>,
+    // fixed march step, also used as the hit tolerance
+    pub step: f32,
+    // rough radius of the scene around the origin, bounds the march
+    pub extent: f32,
+    // smooth-union blend radius between objects, 0 for a hard union
+    pub blend: f32,
+}
+
+impl Scene {
+    /// The classic single donut from the original article.
+    pub fn donut(radii: Vec2, axis: Vec3) -> Self {
+        Self {
+            objects: vec![Object {
+                shape: Shape::Torus { radii, axis },
+                transform: Transform::identity(),
+                color: None,
+                spin: None,
+            }],
+            step: radii.y,
+            extent: radii.x,
+            blend: 0.0,
+        }
+    }
+
+    /// Radius of a sphere around the origin that holds every object.
+    pub fn bounding_radius(&self) -> f32 {
+        self.objects
+            .iter()
+            .map(|o| o.transform.translation.len() + o.shape.bounding_radius() * o.transform.scale)
+            .fold(0.0, f32::max)
+    }
+
+    /// Radii of the single donut, None for every other scene.
+    pub fn donut_radii(&self) -> Option<Vec2> {
+        match self.objects[..] {
+            [Object { shape: Shape::Torus { radii, .. }, .. }] => Some(radii),
+            _ => None,
+        }
+    }
+
+    /// Reshapes the single donut, moving the march step and extent along
+    /// the way `donut` sets them. Other scenes are left alone.
+    pub fn set_donut_radii(&mut self, radii: Vec2) {
+        if let [Object { shape: Shape::Torus { radii: r, .. }, .. }] = &mut self.objects[..] {
+            *r = radii;
+            self.step = radii.y;
+            self.extent = radii.x;
+        }
+    }
+
+    /// A `(p, q)` torus knot about as large as the donut; `(2, 3)` is the
+    /// trefoil.
+    pub fn knot(knot: (u32, u32), axis: Vec3) -> Self {
+        let radii = Vec2::new(0.95, 0.4);
+        let tube = 0.15;
+        Self {
+            objects: vec![Object {
+                shape: Shape::Knot { knot, radii, tube, axis },
+                transform: Transform::identity(),
+                color: None,
+                spin: None,
+            }],
+            // fine enough not to step through the thin tube
+            step: tube * 0.5,
+            extent: radii.x + radii.y,
+            blend: 0.0,
+        }
+    }
+
+    /// `text` in the banner font, extruded: every lit pixel of a glyph is
+    /// a box, the string about 3 wide facing the camera, and at most a fifth
+    /// of a unit per pixel so short words don't dwarf the donut.
+    pub fn text(text: &str) -> Self {
+        // the last character's spacing column isn't drawn
+        let (cols, rows) = ((text_width(text) - 1).max(1), GLYPH_HEIGHT);
+        let pixel = (3.0 / cols as f32).min(0.2);
+        let half = Vec3::new(pixel, pixel * 0.5, pixel * 0.5);
+        // glyph columns run along Y (screen right), rows along Z (down)
+        let objects: Vec<Object> = text_cells(text, 0, 0)
+            .map(|(x, y)| {
+                let center = Vec3::new(
+                    0.0,
+                    (x as f32 + 0.5 - cols as f32 / 2.0) * pixel,
+                    (y as f32 + 0.5 - rows as f32 / 2.0) * pixel,
+                );
+                Object {
+                    shape: Shape::Box { half_extents: half },
+                    transform: Transform::translate(center),
+                    color: None,
+                    spin: None,
+                }
+            })
+            .collect();
+        let mut scene = Self { objects, step: pixel * 0.25, extent: 0.0, blend: 0.0 };
+        scene.extent = scene.bounding_radius();
+        scene
+    }
+
+    /// Both scenes at once, marched finely enough for either.
+    pub fn union(mut self, other: Scene) -> Self {
+        self.objects.extend(other.objects);
+        self.step = self.step.min(other.step);
+        self.extent = self.extent.max(other.extent);
+        self
+    }
+
+    /// `n` interlocking links laid out along Y, alternating between facing the
+    /// camera and lying edge-on, each tinted by the next palette color.
+    pub fn chain(n: usize) -> Self {
+        let radii = Vec2::new(0.5, 0.12);
+        let spacing = 0.7;
+        let objects = (0..n)
+            .map(|i| {
+                let y = (i as f32 - (n as f32 - 1.0) / 2.0) * spacing;
+                let axis = if i % 2 == 0 {
+                    Vec3::new(1.0, 0.0, 0.0)
+                } else {
+                    Vec3::new(0.0, 0.0, 1.0)
+                };
+                Object {
+                    shape: Shape::Torus { radii, axis },
+                    transform: Transform::translate(Vec3::new(0.0, y, 0.0)),
+                    color: Some(GRADIENT[1 + i % (GRADIENT.len() - 1)].1),
+                    spin: None,
+                }
+            })
+            .collect();
+        Self {
+            objects,
+            step: 0.05,
+            extent: (n as f32 - 1.0) / 2.0 * spacing + radii.x + radii.y,
+            blend: 0.0,
+        }
+    }
+
+    /// The classic donut with a second, smaller one turning inside its hole
+    /// around its own axis at its own pace, each in its own color.
+    pub fn twin(radii: Vec2, axis: Vec3) -> Self {
+        let inner = Vec2::new(radii.x * 0.5, radii.y * 0.5);
+        Self {
+            objects: vec![
+                Object {
+                    shape: Shape::Torus { radii, axis },
+                    transform: Transform::identity(),
+                    color: Some(GRADIENT[4].1),
+                    spin: None,
+                },
+                Object {
+                    shape: Shape::Torus { radii: inner, axis: Vec3::new(0.0, 0.0, 1.0) },
+                    transform: Transform::identity(),
+                    color: Some(GRADIENT[2].1),
+                    spin: Some((Vec3::new(0.0, 1.0, 0.0), -2.5)),
+                },
+            ],
+            // fine enough not to step over the thinner inner tube
+            step: inner.y * 0.5,
+            extent: radii.x,
+            blend: 0.0,
+        }
+    }
+
+    /// Turns every spinning object to match the scene spin `angle`.
+    pub fn animate(&mut self, angle: f32) {
+        for object in &mut self.objects {
+            if let Some((axis, rate)) = object.spin {
+                object.transform.rotation = Quat::from_axis_angle(axis, angle * rate);
+            }
+        }
+    }
+
+    /// Union of all objects, smoothed by `blend`.
+    pub fn distance(&self, p: Vec3) -> f32 {
+        if self.blend <= 0.0 {
+            return self.closest(p).0;
+        }
+        self.objects
+            .iter()
+            .map(|o| o.distance(p))
+            .reduce(|a, b| op_smooth_union(a, b, self.blend))
+            .unwrap_or(f32::INFINITY)
+    }
+
+    /// Whether a ray that missed passed through the hole of any ring.
+    pub fn through_hole(&self, ro: Vec3, rd: Vec3) -> bool {
+        self.objects.iter().any(|o| o.through_hole(ro, rd))
+    }
+
+    /// Distance to, and index of, the nearest object.
+    pub fn closest(&self, p: Vec3) -> (f32, usize) {
+        self.objects
+            .iter()
+            .enumerate()
+            .map(|(i, o)| (o.distance(p), i))
+            .fold((f32::INFINITY, 0), |a, b| if b.0 < a.0 { b } else { a })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn twin_axes_diverge_over_frames() {
+        let mut scene = Scene::twin(Vec2::new(1.0, 0.4), Vec3::new(0.0, 0.0, 1.0));
+        let axes = |scene: &Scene| -> Vec<Vec3> {
+            scene
+                .objects
+                .iter()
+                .map(|o| match o.shape {
+                    Shape::Torus { axis, .. } => o.transform.rotation.rotate(axis),
+                    _ => unreachable!(),
+                })
+                .collect()
+        };
+        let alignment: Vec<f32> = [0.0, 0.2, 0.4, 0.6]
+            .into_iter()
+            .map(|angle| {
+                scene.animate(angle);
+                let a = axes(&scene);
+                a[0].dot(a[1])
+            })
+            .collect();
+        assert!((alignment[0] - 1.0).abs() < 1e-5);
+        assert!(alignment.windows(2).all(|w| w[1] < w[0]), "{alignment:?}");
+        assert_ne!(scene.objects[0].color, scene.objects[1].color);
+    }
+
+    #[test]
+    fn chain_of_three_links_along_y() {
+        let scene = Scene::chain(3);
+        assert_eq!(scene.objects.len(), 3);
+        let (x, z) = (Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+        for (object, (y, axis)) in scene.objects.iter().zip([(-0.7, x), (0.0, z), (0.7, x)]) {
+            match object.shape {
+                Shape::Torus { axis: a, .. } => assert_eq!(a, axis),
+                _ => panic!("not a torus: {:?}", object.shape),
+            }
+            assert_eq!(object.transform, Transform::translate(Vec3::new(0.0, y, 0.0)));
+        }
+    }
+
+    #[test]
+    fn translated_sphere_moves_its_surface() {
+        let v = Vec3::new(0.5, -1.0, 2.0);
+        let sphere = Object {
+            shape: Shape::Sphere { radius: 0.75 },
+            transform: Transform::translate(v),
+            color: None,
+            spin: None,
+        };
+        for dir in [Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, -1.0, 0.0), Vec3::new(1.0, 1.0, 1.0).norm()] {
+            assert!(sphere.distance(v.add(dir.mul(0.75))).abs() < 1e-5);
+            assert!(sphere.distance(dir.mul(0.75)).abs() > 0.1);
+        }
+        assert!((sphere.distance(v) + 0.75).abs() < 1e-6);
+    }
+
+    #[test]
+    fn letter_i_is_eleven_pixel_boxes() {
+        let scene = Scene::text("I");
+        // serifs of three pixels top and bottom, a stem of five between
+        let mut expected: Vec<(i32, i32)> = vec![(1, 0), (2, 0), (3, 0), (1, 6), (2, 6), (3, 6)];
+        expected.extend((1..6).map(|row| (2, row)));
+        // five columns wide at the largest pixel, 0.2
+        let mut cells: Vec<(i32, i32)> = scene
+            .objects
+            .iter()
+            .map(|object| {
+                let Shape::Box { half_extents } = object.shape else { panic!("{:?} is not a box", object.shape) };
+                assert_eq!((half_extents.x, half_extents.y, half_extents.z), (0.2, 0.1, 0.1));
+                let at = object.transform.translation;
+                assert_eq!(at.x, 0.0);
+                ((at.y / 0.2 + 2.0).round() as i32, (at.z / 0.2 + 3.0).round() as i32)
+            })
+            .collect();
+        cells.sort();
+        expected.sort();
+        assert_eq!(cells, expected);
+    }
+
+    #[test]
+    fn center_ray_goes_through_the_hole_and_a_tube_ray_does_not() {
+        let scene = Scene::donut(Vec2::new(1.0, 0.4), Vec3::new(0.0, 0.0, 1.0));
+        let down = Vec3::new(0.0, 0.0, -1.0);
+        let from = |x: f32| Vec3::new(x, 0.0, 3.0);
+        assert!(scene.through_hole(from(0.0), down));
+        // straight down onto the tube's middle, the plane is crossed on the major circle
+        assert!(scene.distance(from(1.0).add(down.mul(3.0 - 0.4))).abs() < 1e-4);
+        assert!(!scene.through_hole(from(1.0), down));
+        // the ring's plane lies behind the ray, and a sphere has no hole
+        assert!(!scene.through_hole(from(0.0), down.mul(-1.0)));
+        let sphere = Shape::Sphere { radius: 1.0 };
+        assert!(!sphere.through_hole(from(0.0), down));
+    }
+}
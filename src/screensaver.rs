@@ -0,0 +1,178 @@
+//! "Lava lamp" mode: a seeded, periodically re-rolled blob scene.
+
+use crossterm::style::Color;
+
+use crate::color::{lerp_color, Theme};
+use crate::math::{mix, Vec3};
+use crate::rng::Rng;
+use crate::scene::{Object, Scene, Shape, Transform};
+
+// frames between re-rolls, and how long the cross-fade takes
+pub const PERIOD: u32 = 600;
+pub const TRANSITION: u32 = 120;
+pub const MAX_BLOBS: usize = 7;
+
+/// One sphere bobbing along a Lissajous path around the origin.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Blob {
+    pub radius: f32,
+    pub amplitude: Vec3,
+    // radians per frame
+    pub freq: f32,
+    pub phase: Vec3,
+}
+
+impl Blob {
+    pub fn center(&self, frame: u32) -> Vec3 {
+        let t = frame as f32 * self.freq;
+        Vec3::new(
+            self.amplitude.x * (t + self.phase.x).sin(),
+            self.amplitude.y * (t * 1.3 + self.phase.y).sin(),
+            self.amplitude.z * (t * 0.7 + self.phase.z).sin(),
+        )
+    }
+}
+
+/// Everything the screensaver randomizes.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SaverParams {
+    pub axis: Vec3,
+    // radians per frame
+    pub speed: f32,
+    pub theme: Theme,
+    pub blobs: Vec<Blob>,
+}
+
+impl SaverParams {
+    pub fn random(rng: &mut Rng) -> Self {
+        let axis = Vec3::new(rng.range(-1.0, 1.0), rng.range(-1.0, 1.0), rng.range(-1.0, 1.0));
+        let speed = rng.range(0.3, 1.5).to_radians();
+        let theme = Theme::ALL[rng.range_usize(0, Theme::ALL.len() - 1)];
+        let blobs = (0..rng.range_usize(3, MAX_BLOBS))
+            .map(|_| Blob {
+                radius: rng.range(0.3, 0.55),
+                amplitude: Vec3::new(rng.range(0.2, 0.8), rng.range(0.2, 0.8), rng.range(0.2, 0.8)),
+                freq: rng.range(0.5, 2.0).to_radians(),
+                phase: Vec3::new(rng.range(0.0, 6.3), rng.range(0.0, 6.3), rng.range(0.0, 6.3)),
+            })
+            .collect();
+        Self { axis: axis.norm(), speed, theme, blobs }
+    }
+}
+
+pub struct Screensaver {
+    rng: Rng,
+    from: SaverParams,
+    to: SaverParams,
+    // frame the current transition started on
+    since: u32,
+    // smoothstepped cross-fade weight from `from` toward `to`
+    weight: f32,
+    angle: f32,
+    frame: u32,
+}
+
+impl Screensaver {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Rng::new(seed);
+        let first = SaverParams::random(&mut rng);
+        Self { rng, from: first.clone(), to: first, since: 0, weight: 1.0, angle: 0.0, frame: 0 }
+    }
+
+    /// The parameters the screensaver is currently heading toward.
+    pub fn params(&self) -> &SaverParams {
+        &self.to
+    }
+
+    /// Moves to `frame`, re-rolling the target every `PERIOD` frames.
+    pub fn advance(&mut self, frame: u32) {
+        if frame > 0 && frame.is_multiple_of(PERIOD) {
+            self.from = self.to.clone();
+            self.to = SaverParams::random(&mut self.rng);
+            self.since = frame;
+        }
+        // frames before the last re-roll count as the start of its fade
+        let x = (frame.saturating_sub(self.since) as f32 / TRANSITION as f32).min(1.0);
+        self.weight = x * x * (3.0 - 2.0 * x);
+        self.frame = frame;
+        self.angle += mix(self.from.speed, self.to.speed, self.weight);
+    }
+
+    /// Rotation axis and accumulated angle for the current frame.
+    pub fn rotation(&self) -> (Vec3, f32) {
+        let a = self.from.axis.mul(1.0 - self.weight).add(self.to.axis.mul(self.weight));
+        // nearly opposite axes cancel out midway; turn about the new one there
+        let axis = if a.len() < 1e-3 { self.to.axis } else { a.norm() };
+        (axis, self.angle)
+    }
+
+    /// Smooth-unioned blobs; blobs only present on one side grow from or
+    /// shrink to nothing during the transition.
+    pub fn scene(&self) -> Scene {
+        let n = self.from.blobs.len().max(self.to.blobs.len());
+        let objects = (0..n)
+            .filter_map(|i| {
+                let (a, b) = (self.from.blobs.get(i), self.to.blobs.get(i));
+                let (ra, rb) = (a.map_or(0.0, |b| b.radius), b.map_or(0.0, |b| b.radius));
+                let radius = mix(ra, rb, self.weight);
+                if radius <= 0.0 {
+                    return None;
+                }
+                let ca = a.or(b).unwrap().center(self.frame);
+                let cb = b.or(a).unwrap().center(self.frame);
+                let center = ca.mul(1.0 - self.weight).add(cb.mul(self.weight));
+                Some(Object {
+                    shape: Shape::Sphere { radius },
+                    transform: Transform::translate(center),
+                    color: None,
+                    spin: None,
+                })
+            })
+            .collect();
+        Scene { objects, step: 0.08, extent: 1.6, blend: 0.4 }
+    }
+
+    pub fn color(&self, intensity: f32) -> Color {
+        let a = self.from.theme.color(intensity);
+        let b = self.to.theme.color(intensity);
+        lerp_color(&a, &b, self.weight)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opposite_axes_keep_a_unit_rotation_axis() {
+        let mut saver = Screensaver::new(7);
+        saver.to.axis = saver.from.axis.mul(-1.0);
+        saver.weight = 0.5;
+        let (axis, _) = saver.rotation();
+        assert!((axis.len() - 1.0).abs() < 1e-5);
+        assert_eq!(axis, saver.to.axis);
+    }
+
+    #[test]
+    fn stepping_back_before_a_reroll_does_not_overflow() {
+        let mut saver = Screensaver::new(7);
+        saver.advance(PERIOD);
+        saver.advance(PERIOD - 1);
+        assert_eq!(saver.weight, 0.0);
+        saver.advance(PERIOD + TRANSITION);
+        assert_eq!(saver.weight, 1.0);
+    }
+
+    #[test]
+    fn same_seed_same_scene() {
+        assert_eq!(Screensaver::new(7).params(), Screensaver::new(7).params());
+        assert_ne!(Screensaver::new(7).params(), Screensaver::new(8).params());
+        let (mut a, mut b) = (Screensaver::new(7), Screensaver::new(7));
+        for frame in 0..=PERIOD {
+            a.advance(frame);
+            b.advance(frame);
+        }
+        assert_eq!(a.params(), b.params());
+        assert_eq!(a.rotation(), b.rotation());
+    }
+}
@@ -0,0 +1,386 @@
+use std::f32::consts::TAU;
+
+use crate::math::{mix, orthonormal_basis, Vec2, Vec3};
+
+pub fn sd_torus(p: Vec3, t: Vec2, tdir: Vec3) -> f32 {
+    // project p onto plane orthogonal to tdir,
+    // then pull it onto the major radius circle (length = t.x),
+    // distance to that circle minus tube radius t.y
+    let p_proj = p.sub(tdir.mul(p.dot(tdir)));
+    let p_proj = p_proj.norm().mul(t.x);
+    p_proj.sub(p).len() - t.y
+}
+
+pub fn sd_sphere(p: Vec3, radius: f32) -> f32 {
+    p.len() - radius
+}
+
+/// Polynomial smooth minimum of two distances; `k` is the blend radius; `k`
+/// of 0 is a plain union.
+pub fn op_smooth_union(a: f32, b: f32, k: f32) -> f32 {
+    if k <= 0.0 {
+        return a.min(b);
+    }
+    let h = (0.5 + 0.5 * (b - a) / k).clamp(0.0, 1.0);
+    mix(b, a, h) - k * h * (1.0 - h)
+}
+
+/// Distance to the plane with unit normal `n` at `offset` from the origin,
+/// positive on the side `n` points to.
+pub fn sd_plane(p: Vec3, n: Vec3, offset: f32) -> f32 {
+    p.dot(n) - offset
+}
+
+/// Intersection of two shapes: only what is inside both. Keeps a lower
+/// bound on the distance, so it is still safe to march.
+pub fn op_intersect(a: f32, b: f32) -> f32 {
+    a.max(b)
+}
+
+/// Uniformly scales any SDF by `s`, which must be > 0: the primitive is
+/// evaluated at `p / s` and the distance scaled back so it stays a true distance.
+pub fn op_scale<F: Fn(Vec3) -> f32>(p: Vec3, s: f32, sdf: F) -> f32 {
+    sdf(p.mul(1.0 / s)) * s
+}
+
+/// Central-difference gradient of an arbitrary SDF, normalized.
+pub fn estimate_normal<F: Fn(Vec3) -> f32>(p: Vec3, sdf: F) -> Vec3 {
+    let eps = 0.005;
+    let dx = sdf(Vec3::new(p.x + eps, p.y, p.z)) - sdf(Vec3::new(p.x - eps, p.y, p.z));
+    let dy = sdf(Vec3::new(p.x, p.y + eps, p.z)) - sdf(Vec3::new(p.x, p.y - eps, p.z));
+    let dz = sdf(Vec3::new(p.x, p.y, p.z + eps)) - sdf(Vec3::new(p.x, p.y, p.z - eps));
+    Vec3::new(dx, dy, dz).norm()
+}
+
+/// Blend from the `from` shape at `t = 0` to the `to` shape at `t = 1`.
+/// In between it is only an approximate distance, fine for a fixed-step
+/// marcher.
+pub fn op_morph<A: Fn(Vec3) -> f32, B: Fn(Vec3) -> f32>(p: Vec3, from: A, to: B, t: f32) -> f32 {
+    mix(from(p), to(p), t)
+}
+
+/// `estimate_normal` for a hit at `p` seen along `rd`, taken `offset` back
+/// toward the camera. That is just outside the surface, where convex edges
+/// are rounded off, so hits that land a little inside them stop flipping
+/// between the faces.
+pub fn estimate_hit_normal<F: Fn(Vec3) -> f32>(p: Vec3, rd: Vec3, offset: f32, sdf: F) -> Vec3 {
+    estimate_normal(p.sub(rd.mul(offset)), sdf)
+}
+
+/// Largest gradient magnitude of `sdf` found on a `samples`³ grid spanning
+/// the box `bounds` (min and max corner). Exact distance fields come out at
+/// 1 up to float noise (`sd_torus`, `sd_sphere`, `sd_box`); well above 1
+/// means sphere tracing can overshoot, as expected for deformation operators
+/// like `op_displace` (about 1.7 for amplitude 0.1 at frequency 8).
+pub fn check_lipschitz<F: Fn(Vec3) -> f32>(sdf: F, bounds: (Vec3, Vec3), samples: usize) -> f32 {
+    let (lo, hi) = bounds;
+    let n = samples.max(2);
+    let at = |a: f32, b: f32, i: usize| a + (b - a) * i as f32 / (n - 1) as f32;
+    let eps = 1e-3;
+    let mut max = 0.0_f32;
+    for i in 0..n {
+        for j in 0..n {
+            for k in 0..n {
+                let p = Vec3::new(at(lo.x, hi.x, i), at(lo.y, hi.y, j), at(lo.z, hi.z, k));
+                let grad = Vec3::new(
+                    sdf(Vec3::new(p.x + eps, p.y, p.z)) - sdf(Vec3::new(p.x - eps, p.y, p.z)),
+                    sdf(Vec3::new(p.x, p.y + eps, p.z)) - sdf(Vec3::new(p.x, p.y - eps, p.z)),
+                    sdf(Vec3::new(p.x, p.y, p.z + eps)) - sdf(Vec3::new(p.x, p.y, p.z - eps)),
+                );
+                max = max.max(grad.len() / (2.0 * eps));
+            }
+        }
+    }
+    max
+}
+
+/// Segments per unit of `p + q` along the knot curve of `sd_torus_knot`.
+const KNOT_SEGMENTS: u32 = 48;
+
+/// Approximate distance to a `(p, q)` torus knot: a tube of radius `tube`
+/// around a curve that goes `p` times around the major circle of radius
+/// `t.x` while winding `q` times around it at radius `t.y`. `(1, 0)` never
+/// leaves its starting point on the winding, so it is the plain torus of
+/// major radius `t.x + t.y`.
+///
+/// The curve is a polyline, so the result sits up to the chord sag off the
+/// true tube, and far away it returns the distance to the surrounding torus
+/// instead. Both stay below the real distance, safe for sphere tracing, but
+/// the tube is thin: keep the fixed march step under about half of `tube`.
+pub fn sd_torus_knot(p: Vec3, knot: (u32, u32), t: Vec2, tube: f32, tdir: Vec3) -> f32 {
+    // the whole knot lies inside this torus; skip the curve well outside it
+    let bound = sd_torus(p, Vec2::new(t.x, t.y + tube), tdir);
+    if bound > tube {
+        return bound;
+    }
+    let (a, b) = orthonormal_basis(tdir);
+    let (wind_p, wind_q) = (knot.0 as f32, knot.1 as f32);
+    let curve = |s: f32| {
+        let (sin_p, cos_p) = (wind_p * s).sin_cos();
+        let (sin_q, cos_q) = (wind_q * s).sin_cos();
+        let r = t.x + t.y * cos_q;
+        a.mul(r * cos_p).add(b.mul(r * sin_p)).add(tdir.mul(t.y * sin_q))
+    };
+
+    let segments = KNOT_SEGMENTS * (knot.0 + knot.1).max(1);
+    let mut from = curve(0.0);
+    let mut nearest = f32::INFINITY;
+    for i in 1..=segments {
+        let to = curve(i as f32 / segments as f32 * TAU);
+        // squared distance to the segment from..to
+        let (ab, ap) = (to.sub(from), p.sub(from));
+        let h = (ap.dot(ab) / ab.dot(ab).max(1e-12)).clamp(0.0, 1.0);
+        let d = ap.sub(ab.mul(h));
+        nearest = nearest.min(d.dot(d));
+        from = to;
+    }
+    nearest.sqrt() - tube
+}
+
+pub fn torus_normal(p: Vec3, t: Vec2, tdir: Vec3) -> Vec3 {
+    estimate_normal(p, |q| sd_torus(q, t, tdir))
+}
+
+/// Exact distance to a capped cone with its apex at the origin, opening down
+/// the -Y axis to a base disc at `y = -height`. `angle_rad` is the half-angle
+/// between the axis and the slanted side.
+pub fn sd_cone(p: Vec3, angle_rad: f32, height: f32) -> f32 {
+    // work in the 2D (radial, axial) half-plane; q is the base rim point
+    let (qx, qy) = (height * angle_rad.tan(), -height);
+    let (wx, wy) = ((p.x * p.x + p.z * p.z).sqrt(), p.y);
+
+    // closest point on the slanted side and on the base disc
+    let ta = ((wx * qx + wy * qy) / (qx * qx + qy * qy)).clamp(0.0, 1.0);
+    let (ax, ay) = (wx - qx * ta, wy - qy * ta);
+    let tb = (wx / qx).clamp(0.0, 1.0);
+    let (bx, by) = (wx - qx * tb, wy - qy);
+
+    let d = (ax * ax + ay * ay).min(bx * bx + by * by);
+    // inside when below the slant and above the base (q.y < 0 flips both tests)
+    let s = (-(wx * qy - wy * qx)).max(-(wy - qy));
+    d.sqrt() * s.signum()
+}
+
+/// Exact distance to an octahedron centered at the origin with its vertices
+/// `size` away along each axis.
+pub fn sd_octahedron(p: Vec3, size: f32) -> f32 {
+    let p = p.abs();
+    let m = p.x + p.y + p.z - size;
+    // pick the axis permutation whose face region contains p
+    let q = if 3.0 * p.x < m {
+        p
+    } else if 3.0 * p.y < m {
+        Vec3::new(p.y, p.z, p.x)
+    } else if 3.0 * p.z < m {
+        Vec3::new(p.z, p.x, p.y)
+    } else {
+        return m * 0.577_350_27; // 1/sqrt(3): distance to the face plane
+    };
+    let k = (0.5 * (q.z - q.y + size)).clamp(0.0, size);
+    Vec3::new(q.x, q.y - size + k, q.z - k).len()
+}
+
+/// Ellipsoid centered at the origin with semi-axes `radii`.
+///
+/// This is the usual `k0 * (k0 - 1) / k1` approximation: it is a bound, not
+/// an exact distance, so the marcher should step conservatively near it.
+pub fn sd_ellipsoid(p: Vec3, radii: Vec3) -> f32 {
+    let k0 = Vec3::new(p.x / radii.x, p.y / radii.y, p.z / radii.z).len();
+    let k1 = Vec3::new(
+        p.x / (radii.x * radii.x),
+        p.y / (radii.y * radii.y),
+        p.z / (radii.z * radii.z),
+    )
+    .len();
+    k0 * (k0 - 1.0) / k1
+}
+
+/// Exact distance to an axis-aligned box centered at the origin.
+pub fn sd_box(p: Vec3, half_extents: Vec3) -> f32 {
+    let q = p.abs().sub(half_extents);
+    let outside = Vec3::new(q.x.max(0.0), q.y.max(0.0), q.z.max(0.0)).len();
+    outside + q.x.max(q.y).max(q.z).min(0.0)
+}
+
+/// Adds a sinusoidal ripple of amplitude `amp` and spatial frequency `freq`
+/// to `base_sdf`. The result is no longer an exact distance (its gradient can
+/// exceed 1 by roughly `amp * freq`), so march steps must shrink accordingly.
+pub fn op_displace<F: Fn(Vec3) -> f32>(p: Vec3, base_sdf: F, amp: f32, freq: f32) -> f32 {
+    base_sdf(p) + amp * (freq * p.x).sin() * (freq * p.y).sin() * (freq * p.z).sin()
+}
+
+/// Surface coordinates of `p` on the torus, both in `[0, 1)`: `u` runs around
+/// the major circle and `v` around the tube.
+pub fn torus_uv(p: Vec3, t: Vec2, tdir: Vec3) -> (f32, f32) {
+    let (a, b) = orthonormal_basis(tdir);
+    let u = p.dot(b).atan2(p.dot(a)) / TAU;
+
+    // offset from the nearest point on the major circle, split into the
+    // outward radial direction and the torus axis
+    let radial = p.sub(tdir.mul(p.dot(tdir))).norm();
+    let w = p.sub(radial.mul(t.x));
+    let v = w.dot(tdir).atan2(w.dot(radial)) / TAU;
+
+    // rem_euclid of a tiny negative angle rounds up to exactly 1.0
+    let wrap = |x: f32| {
+        let r = x.rem_euclid(1.0);
+        if r < 1.0 { r } else { 0.0 }
+    };
+    (wrap(u), wrap(v))
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hit_normal_offset_steadies_a_grazing_edge() {
+        let cube = |p: Vec3| sd_box(p, Vec3::new(1.0, 1.0, 1.0));
+        let rd = Vec3::new(-1.0, -1.0, 0.05).norm();
+        // one hit per frame, sliding over the edge at x = y = 1 and landing
+        // a little inside it, as hits within the march's threshold do
+        let spread = |offset: f32| {
+            let normals: Vec<Vec3> = (0..40)
+                .map(|k| {
+                    let s = -0.02 + 0.001 * k as f32;
+                    let depth = 0.002 * (k % 3) as f32;
+                    let (x, y) = if s < 0.0 { (1.0, 1.0 + s) } else { (1.0 - s, 1.0) };
+                    estimate_hit_normal(Vec3::new(x - depth, y - depth, 0.0), rd, offset, cube)
+                })
+                .collect();
+            normals.windows(2).map(|w| w[1].sub(w[0]).dot(w[1].sub(w[0]))).sum::<f32>() / 39.0
+        };
+        assert!(spread(0.05) < spread(0.0) * 0.5, "{} vs {}", spread(0.05), spread(0.0));
+    }
+
+    #[test]
+    fn scaled_unit_sphere_is_the_radius_2_sphere() {
+        for p in [Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 2.0, -0.5), Vec3::new(3.0, 0.0, 0.0)] {
+            let scaled = op_scale(p, 2.0, |q| sd_sphere(q, 1.0));
+            assert!((scaled - sd_sphere(p, 2.0)).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn cone_at_apex_rim_and_side() {
+        let cone = |x: f32, y: f32| sd_cone(Vec3::new(x, y, 0.0), 45f32.to_radians(), 1.0);
+        assert!(cone(0.0, 0.0).abs() < 1e-5);
+        assert!(cone(1.0, -1.0).abs() < 1e-5);
+        assert!(cone(0.5, -0.5).abs() < 1e-5);
+        // a tenth out from the side along its outward normal, and in
+        let out = 0.1 * std::f32::consts::FRAC_1_SQRT_2;
+        assert!((cone(0.5 + out, -0.5 + out) - 0.1).abs() < 1e-5);
+        assert!((cone(0.5 - out, -0.5 - out) + 0.1).abs() < 1e-5);
+        // straight above the apex and below the base
+        assert!((cone(0.0, 0.5) - 0.5).abs() < 1e-5);
+        assert!((cone(0.0, -1.25) - 0.25).abs() < 1e-5);
+    }
+
+    #[test]
+    fn octahedron_at_vertices_faces_and_center() {
+        let size = 1.5;
+        for v in [Vec3::new(size, 0.0, 0.0), Vec3::new(0.0, -size, 0.0), Vec3::new(0.0, 0.0, size)] {
+            assert!(sd_octahedron(v, size).abs() < 1e-5);
+            assert!((sd_octahedron(v.mul(1.2), size) - 0.2 * size).abs() < 1e-5);
+        }
+        let face = Vec3::new(1.0, 1.0, 1.0).mul(size / 3.0);
+        assert!(sd_octahedron(face, size).abs() < 1e-5);
+        let normal = Vec3::new(1.0, 1.0, 1.0).norm();
+        assert!((sd_octahedron(face.add(normal.mul(0.1)), size) - 0.1).abs() < 1e-5);
+        let center = sd_octahedron(Vec3::default(), size);
+        assert!((center + size / 3f32.sqrt()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn round_ellipsoid_is_a_sphere() {
+        let radii = Vec3::new(0.8, 0.8, 0.8);
+        for p in [Vec3::new(0.1, 0.2, 0.0), Vec3::new(0.8, 0.0, 0.0), Vec3::new(-1.0, 2.0, 0.5)] {
+            assert!((sd_ellipsoid(p, radii) - sd_sphere(p, 0.8)).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn displacement_of_zero_amplitude_is_the_base() {
+        let base = |p: Vec3| sd_torus(p, Vec2::new(1.0, 0.3), Vec3::new(0.0, 0.0, 1.0));
+        for p in [Vec3::new(0.3, -0.4, 0.2), Vec3::new(1.3, 0.0, 0.0), Vec3::new(-2.0, 1.0, 0.7)] {
+            assert_eq!(op_displace(p, base, 0.0, 8.0), base(p));
+        }
+    }
+
+    #[test]
+    fn opposite_points_are_half_a_period_apart() {
+        let (t, axis) = (Vec2::new(1.0, 0.3), Vec3::new(0.0, 0.0, 1.0));
+        let half = |a: f32, b: f32| ((a - b).rem_euclid(1.0) - 0.5).abs() < 1e-5;
+        let same = |a: f32, b: f32| (a - b).abs() < 1e-5;
+        for angle in [0.0f32, 0.7, 2.0, 4.5] {
+            let radial = Vec3::new(angle.cos(), angle.sin(), 0.0);
+            let (outer, inner) = (radial.mul(t.x + t.y), radial.mul(t.x - t.y));
+            let ((uo, vo), (ui, vi)) = (torus_uv(outer, t, axis), torus_uv(inner, t, axis));
+            // across the tube
+            assert!(same(uo, ui) && half(vo, vi), "{uo} {vo} vs {ui} {vi}");
+            // across the hole
+            let (ua, va) = torus_uv(outer.mul(-1.0), t, axis);
+            assert!(half(uo, ua) && same(vo, va), "{uo} {vo} vs {ua} {va}");
+        }
+    }
+
+    #[test]
+    fn morph_starts_at_the_sphere_and_ends_at_the_torus() {
+        let sphere = |p: Vec3| sd_sphere(p, 1.4);
+        let torus = |p: Vec3| sd_torus(p, Vec2::new(1.0, 0.4), Vec3::new(0.0, 0.0, 1.0));
+        for p in [Vec3::new(0.2, 0.1, 0.0), Vec3::new(1.0, 0.0, 0.4), Vec3::new(-2.0, 0.5, 1.0)] {
+            assert_eq!(op_morph(p, sphere, torus, 0.0), sphere(p));
+            assert!((op_morph(p, sphere, torus, 1.0) - torus(p)).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn exact_fields_are_1_lipschitz_and_displacement_is_not() {
+        let bounds = (Vec3::new(-2.0, -2.0, -2.0), Vec3::new(2.0, 2.0, 2.0));
+        let torus = |p: Vec3| sd_torus(p, Vec2::new(1.0, 0.3), Vec3::new(0.0, 0.0, 1.0));
+        let exact: [(&str, &dyn Fn(Vec3) -> f32); 3] = [
+            ("torus", &torus),
+            ("sphere", &|p| sd_sphere(p, 1.2)),
+            ("box", &|p| sd_box(p, Vec3::new(1.0, 0.5, 0.8))),
+        ];
+        for (name, sdf) in exact {
+            let max = check_lipschitz(sdf, bounds, 16);
+            assert!(max <= 1.01, "{name}: {max}");
+        }
+        let displaced = check_lipschitz(|p| op_displace(p, torus, 0.1, 8.0), bounds, 16);
+        assert!(displaced > 1.2, "{displaced}");
+    }
+
+    #[test]
+    fn clipped_side_of_the_torus_is_empty() {
+        let torus = |p: Vec3| sd_torus(p, Vec2::new(1.0, 0.3), Vec3::new(0.0, 0.0, 1.0));
+        // keep x < 0.2: the plane's normal points into the cut-away half
+        let (n, offset) = (Vec3::new(1.0, 0.0, 0.0), 0.2);
+        let clipped = |p: Vec3| op_intersect(torus(p), sd_plane(p, n, offset));
+        // inside the tube on the cut side, then on the kept side
+        for p in [Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.7, 0.7, 0.1)] {
+            assert!(torus(p) < 0.0);
+            assert!(clipped(p) > 0.0, "{p:?}: {}", clipped(p));
+        }
+        let kept = Vec3::new(-1.0, 0.0, 0.1);
+        assert_eq!(clipped(kept), torus(kept));
+        // the cap: right on the plane inside the tube
+        assert!(clipped(Vec3::new(0.2, 1.0, 0.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn knot_1_0_is_the_plain_torus() {
+        let (t, tube, axis) = (Vec2::new(1.0, 0.3), 0.2, Vec3::new(0.0, 0.0, 1.0));
+        let torus = |p: Vec3| sd_torus(p, Vec2::new(t.x + t.y, tube), axis);
+        for angle in [0.0f32, 0.4, 1.9, 3.3, 5.8] {
+            let radial = Vec3::new(angle.cos(), angle.sin(), 0.0);
+            for (out, up) in [(0.0, 0.0), (0.15, 0.05), (-0.1, 0.2), (0.3, -0.1)] {
+                let p = radial.mul(t.x + t.y + out).add(axis.mul(up));
+                // within the polyline's chord sag of the true tube
+                let (knot, exact) = (sd_torus_knot(p, (1, 0), t, tube, axis), torus(p));
+                assert!((knot - exact).abs() < 5e-3, "{p:?}: {knot} vs {exact}");
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use crossterm::style::Color;
+
+const GLYPHS: &[u8] = b".*+";
+
+/// Sparse, stable field of stars for cells whose ray missed everything.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Starfield {
+    // fraction of background cells that hold a star, 0..=1
+    pub density: f32,
+    pub seed: u64,
+    // modulate each star's brightness over time
+    pub twinkle: bool,
+}
+
+impl Starfield {
+    /// The star at cell (i, j), if any. Which cells are stars depends only on
+    /// the cell and the seed, so the field doesn't flicker between frames.
+    pub fn star(&self, i: u16, j: u16, frame: u32) -> Option<(u8, Color)> {
+        let h = hash_cell(i, j, self.seed);
+        // top 24 bits decide presence, the rest pick glyph and phase
+        if ((h >> 40) as f32 / (1u64 << 24) as f32) >= self.density {
+            return None;
+        }
+        let glyph = GLYPHS[(h % GLYPHS.len() as u64) as usize];
+        let mut level = 0.5 + 0.5 * ((h >> 8) & 0xff) as f32 / 255.0;
+        if self.twinkle {
+            let phase = ((h >> 16) & 0xff) as f32 / 255.0 * std::f32::consts::TAU;
+            level *= 0.65 + 0.35 * (frame as f32 * 0.1 + phase).sin();
+        }
+        let v = (level * 255.0).round() as u8;
+        Some((glyph, Color::Rgb { r: v, g: v, b: v }))
+    }
+}
+
+/// SplitMix64 finalizer over the cell coordinates and seed.
+pub fn hash_cell(i: u16, j: u16, seed: u64) -> u64 {
+    let mut z = seed
+        .wrapping_add(((i as u64) << 16 | j as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_keeps_the_same_stars() {
+        let stars = |field: Starfield, frame: u32| -> Vec<(u16, u16)> {
+            (0..80u16)
+                .flat_map(|i| (0..24u16).map(move |j| (i, j)))
+                .filter(|&(i, j)| field.star(i, j, frame).is_some())
+                .collect()
+        };
+        let field = Starfield { density: 0.05, seed: 42, twinkle: true };
+        let first = stars(field, 0);
+        assert!(!first.is_empty());
+        assert_eq!(stars(field, 1), first);
+        assert_eq!(stars(field, 500), first);
+        assert_ne!(stars(Starfield { seed: 43, ..field }, 0), first);
+    }
+}
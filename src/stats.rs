@@ -0,0 +1,272 @@
+use std::time::Duration;
+
+use crate::color::to_rgb;
+use crate::render::Frame;
+
+/// 64-bit FNV-1a over the frame's size, glyphs and RGB colors. Fixed
+/// constants and byte order, so the value is the same on every platform and
+/// across Rust versions, unlike `DefaultHasher`.
+pub fn checksum(frame: &Frame) -> u64 {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    let size = [frame.width.to_le_bytes(), frame.height.to_le_bytes()].concat();
+    let colors = frame.colors.iter().flat_map(|&c| {
+        let (r, g, b) = to_rgb(c);
+        [r, g, b]
+    });
+    size.into_iter()
+        .chain(frame.chars.iter().copied())
+        .chain(colors)
+        .fold(OFFSET, |h, byte| (h ^ byte as u64).wrapping_mul(PRIME))
+}
+
+/// Per-frame numbers for `--stats`, aggregated over every cell's ray.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FrameStats {
+    pub frame: u32,
+    pub render_ms: f64,
+    pub march_steps: u64,
+    pub hits: usize,
+    // nearest and farthest hit distance; None when nothing was hit
+    pub min_depth: Option<f32>,
+    pub max_depth: Option<f32>,
+}
+
+impl FrameStats {
+    pub fn new(t: u32, frame: &Frame, render_time: Duration) -> Self {
+        let hits = frame.depth.iter().copied().filter(|d| d.is_finite());
+        Self {
+            frame: t,
+            render_ms: render_time.as_secs_f64() * 1000.0,
+            march_steps: frame.steps.iter().map(|&s| s as u64).sum(),
+            hits: hits.clone().count(),
+            min_depth: hits.clone().reduce(f32::min),
+            max_depth: hits.reduce(f32::max),
+        }
+    }
+
+    /// One JSON object on a single line, for appending to a `.jsonl` file.
+    pub fn to_json(&self) -> String {
+        let depth = |d: Option<f32>| d.map_or("null".to_string(), |d| d.to_string());
+        format!(
+            "{{\"frame\":{},\"render_ms\":{:.3},\"march_steps\":{},\"hits\":{},\"min_depth\":{},\"max_depth\":{}}}",
+            self.frame,
+            self.render_ms,
+            self.march_steps,
+            self.hits,
+            depth(self.min_depth),
+            depth(self.max_depth),
+        )
+    }
+}
+
+/// Frame times counted into equal-width buckets from the fastest to the
+/// slowest frame.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Histogram {
+    pub start: Duration,
+    pub width: Duration,
+    pub counts: Vec<usize>,
+}
+
+impl Histogram {
+    pub fn new(times: &[Duration], buckets: usize) -> Self {
+        let buckets = buckets.max(1);
+        let start = times.iter().copied().min().unwrap_or_default();
+        let end = times.iter().copied().max().unwrap_or_default();
+        let width = (end - start) / buckets as u32;
+        let mut counts = vec![0; buckets];
+        for &t in times {
+            // the slowest frame lands on the upper edge of the last bucket
+            let i = match width.as_nanos() {
+                0 => 0,
+                w => ((t - start).as_nanos() / w) as usize,
+            };
+            counts[i.min(buckets - 1)] += 1;
+        }
+        Self { start, width, counts }
+    }
+
+    /// One line per bucket: its lower bound, a bar and the count.
+    pub fn to_text(&self) -> String {
+        const BAR: usize = 40;
+        let most = self.counts.iter().copied().max().unwrap_or(0).max(1);
+        self.counts
+            .iter()
+            .enumerate()
+            .map(|(i, &n)| {
+                let from = self.start + self.width * i as u32;
+                let bar = "#".repeat((n * BAR).div_ceil(most));
+                format!("{:8.2} ms | {bar:<BAR$} {n}", from.as_secs_f64() * 1000.0)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Throughput summary for `--bench`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BenchSummary {
+    pub frames: usize,
+    pub fps: f64,
+    pub p50: Duration,
+    pub p99: Duration,
+}
+
+impl BenchSummary {
+    /// Summarizes per-frame render `times` from a run that took `total`.
+    pub fn new(times: &[Duration], total: Duration) -> Self {
+        let mut sorted = times.to_vec();
+        sorted.sort();
+        // nearest-rank percentile
+        let rank = |p: f64| {
+            let i = ((p * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len().max(1));
+            sorted.get(i - 1).copied().unwrap_or_default()
+        };
+        let secs = total.as_secs_f64();
+        Self {
+            frames: times.len(),
+            fps: if secs > 0.0 { times.len() as f64 / secs } else { 0.0 },
+            p50: rank(0.5),
+            p99: rank(0.99),
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        let ms = |d: Duration| d.as_secs_f64() * 1000.0;
+        format!(
+            "{} frames, {:.1} fps, p50 {:.2} ms, p99 {:.2} ms",
+            self.frames,
+            self.fps,
+            ms(self.p50),
+            ms(self.p99)
+        )
+    }
+}
+
+/// Time one frame spent in each stage.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct StageTimes {
+    // the geometry pass: tracing every ray and estimating normals
+    pub march: Duration,
+    // lighting and coloring each cell
+    pub shade: Duration,
+    // full-frame effects after shading, like bloom or outlines
+    pub post: Duration,
+    // quantizing, scaling and writing the frame to the terminal
+    pub write: Duration,
+}
+
+impl StageTimes {
+    const NAMES: [&str; 4] = ["march", "shade", "post", "write"];
+
+    fn to_array(self) -> [Duration; 4] {
+        [self.march, self.shade, self.post, self.write]
+    }
+}
+
+/// Per-stage totals over a run, for `--profile`.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Profile {
+    pub total: StageTimes,
+    pub frames: u32,
+}
+
+impl Profile {
+    pub fn add(&mut self, times: StageTimes) {
+        self.total.march += times.march;
+        self.total.shade += times.shade;
+        self.total.post += times.post;
+        self.total.write += times.write;
+        self.frames += 1;
+    }
+
+    /// Mean time per frame in each stage; zero before any frame.
+    pub fn average(&self) -> StageTimes {
+        let n = self.frames.max(1);
+        StageTimes {
+            march: self.total.march / n,
+            shade: self.total.shade / n,
+            post: self.total.post / n,
+            write: self.total.write / n,
+        }
+    }
+
+    /// One line per stage: its mean time per frame and share of the total.
+    pub fn to_text(&self) -> String {
+        let average = self.average().to_array();
+        let sum: Duration = average.iter().sum();
+        StageTimes::NAMES
+            .iter()
+            .zip(average)
+            .map(|(name, d)| {
+                let share = if sum.is_zero() { 0.0 } else { d.as_secs_f64() / sum.as_secs_f64() * 100.0 };
+                format!("{name:>6} {:8.3} ms {share:5.1}%", d.as_secs_f64() * 1000.0)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn stats_json_has_every_key_with_its_type() {
+        let mut frame = Frame::new(4, 1);
+        frame.depth = vec![2.5, f32::INFINITY, 1.25, f32::INFINITY];
+        frame.steps = vec![10, 64, 7, 64];
+        let json = FrameStats::new(12, &frame, Duration::from_micros(1500)).to_json();
+        assert_eq!(
+            json,
+            r#"{"frame":12,"render_ms":1.500,"march_steps":145,"hits":2,"min_depth":1.25,"max_depth":2.5}"#
+        );
+        // a frame without hits has no depth range
+        let empty = FrameStats::new(0, &Frame::new(4, 1), ms(1)).to_json();
+        assert!(empty.ends_with(r#""hits":0,"min_depth":null,"max_depth":null}"#));
+    }
+
+    #[test]
+    fn histogram_buckets_frame_times() {
+        let times = [10, 11, 12, 14, 15, 19, 20].map(ms);
+        let histogram = Histogram::new(&times, 5);
+        assert_eq!((histogram.start, histogram.width), (ms(10), ms(2)));
+        // the slowest frame lands in the last bucket, not past it
+        assert_eq!(histogram.counts, [2, 1, 2, 0, 2]);
+        assert_eq!(Histogram::new(&[ms(5); 3], 4).counts, [3, 0, 0, 0]);
+        assert_eq!(histogram.to_text().lines().count(), 5);
+    }
+
+    #[test]
+    fn bench_summary_of_synthetic_frames() {
+        // 100 frames of 1..=100 ms in shuffled order over 5 s
+        let times: Vec<Duration> = (1..=100).map(|k| ms(k * 37 % 101)).collect();
+        let summary = BenchSummary::new(&times, Duration::from_secs(5));
+        assert_eq!(summary.frames, 100);
+        assert!((summary.fps - 20.0).abs() < 1e-9);
+        assert_eq!((summary.p50, summary.p99), (ms(50), ms(99)));
+        assert_eq!(summary.to_text(), "100 frames, 20.0 fps, p50 50.00 ms, p99 99.00 ms");
+
+        let empty = BenchSummary::new(&[], Duration::ZERO);
+        assert_eq!((empty.frames, empty.fps, empty.p99), (0, 0.0, Duration::ZERO));
+    }
+
+    #[test]
+    fn profile_averages_each_stage_over_frames() {
+        let mut profile = Profile::default();
+        assert_eq!(profile.average(), StageTimes::default());
+        for (march, shade) in [(10, 2), (20, 4), (30, 6)] {
+            profile.add(StageTimes { march: ms(march), shade: ms(shade), post: ms(1), write: ms(3) });
+        }
+        assert_eq!(profile.frames, 3);
+        assert_eq!(profile.average(), StageTimes { march: ms(20), shade: ms(4), post: ms(1), write: ms(3) });
+        let text = profile.to_text();
+        assert_eq!(text.lines().next(), Some(" march   20.000 ms  71.4%"));
+        assert_eq!(text.lines().count(), 4);
+    }
+}